@@ -0,0 +1,200 @@
+//! Shared measurement trigger of all analyzed cameras.
+//!
+//! Every camera collects its noise window stacks in its own thread. Before a camera starts a new stack it waits until
+//! all cameras finished their previous stack, so that the batches of the cameras start together and cover the same
+//! time span. A single camera never waits.
+//!
+//! Cameras that did not add a frame to their stack for a while, e.g. a paused pipeline, a camera without noise window
+//! or a stalled device, are not waited for. They take part again with their next finished stack.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+// time without a new frame after which a camera is not waited for
+const STALL_TIME: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct Participant {
+    waiting: bool,
+    last_frame: Instant,
+}
+
+#[derive(Debug, Default)]
+struct TriggerState {
+    participants: BTreeMap<u64, Participant>,
+    next_id: u64,
+    // counts the released batches, waiting cameras are released once it changes
+    batch: u64,
+}
+
+impl TriggerState {
+    // true if every camera that still collects frames is waiting
+    fn is_ready(&self) -> bool {
+        self.participants
+            .values()
+            .all(|p| p.waiting || p.last_frame.elapsed() > STALL_TIME)
+    }
+
+    fn release(&mut self) {
+        let now = Instant::now();
+        for participant in self.participants.values_mut().filter(|p| p.waiting) {
+            participant.waiting = false;
+            // the released cameras start collecting now
+            participant.last_frame = now;
+        }
+        self.batch += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BatchTrigger {
+    state: Mutex<TriggerState>,
+    released: Condvar,
+}
+
+impl BatchTrigger {
+    /// Adds a camera to the cameras the batches wait for, the camera leaves when the handle is dropped.
+    pub(crate) fn join(self: &Arc<Self>) -> TriggerHandle {
+        let mut state = self.state.lock().expect("Batch trigger Mutex poisened");
+        let id = state.next_id;
+        state.next_id += 1;
+        state.participants.insert(
+            id,
+            Participant {
+                waiting: false,
+                last_frame: Instant::now(),
+            },
+        );
+        TriggerHandle {
+            trigger: self.clone(),
+            id,
+        }
+    }
+}
+
+pub(crate) struct TriggerHandle {
+    trigger: Arc<BatchTrigger>,
+    id: u64,
+}
+
+impl TriggerHandle {
+    /// Waits until all cameras that collect frames are ready for the next batch, returns false if this is the only
+    /// camera.
+    pub(crate) fn wait(&self) -> bool {
+        let mut state = self.trigger.state.lock().expect("Batch trigger Mutex poisened");
+        if state.participants.len() <= 1 {
+            return false;
+        }
+
+        if let Some(participant) = state.participants.get_mut(&self.id) {
+            participant.waiting = true;
+        }
+        let batch = state.batch;
+        // stalled cameras are detected while waiting, so the waiting time is limited
+        while state.batch == batch {
+            if state.is_ready() {
+                state.release();
+                self.trigger.released.notify_all();
+                break;
+            }
+            state = self
+                .trigger
+                .released
+                .wait_timeout(state, STALL_TIME)
+                .expect("Batch trigger Mutex poisened")
+                .0;
+        }
+        true
+    }
+
+    /// Marks that the camera added a frame to its stack, cameras without new frames are not waited for.
+    pub(crate) fn frame_added(&self) {
+        let mut state = self.trigger.state.lock().expect("Batch trigger Mutex poisened");
+        if let Some(participant) = state.participants.get_mut(&self.id) {
+            participant.last_frame = Instant::now();
+        }
+    }
+}
+
+impl Drop for TriggerHandle {
+    fn drop(&mut self) {
+        let mut state = self.trigger.state.lock().expect("Batch trigger Mutex poisened");
+        state.participants.remove(&self.id);
+        // the cameras waiting for the leaving camera start without it
+        if state.participants.values().any(|p| p.waiting) && state.is_ready() {
+            state.release();
+            self.trigger.released.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn single_camera_never_waits() {
+        let trigger = Arc::new(BatchTrigger::default());
+        let handle = trigger.join();
+        let start = Instant::now();
+        assert!(!handle.wait());
+        assert!(start.elapsed() < STALL_TIME);
+    }
+
+    #[test]
+    fn batch_starts_when_all_cameras_are_ready() {
+        let trigger = Arc::new(BatchTrigger::default());
+        let first = trigger.join();
+        let second = trigger.join();
+
+        let start = Instant::now();
+        let waiting = thread::spawn(move || (first.wait(), start.elapsed()));
+        // the second camera is still collecting its stack
+        for _ in 0..4 {
+            thread::sleep(Duration::from_millis(50));
+            second.frame_added();
+        }
+        assert!(!waiting.is_finished());
+        assert!(second.wait());
+
+        let (waited, elapsed) = waiting.join().unwrap();
+        assert!(waited);
+        assert!(elapsed >= Duration::from_millis(200), "{elapsed:?}");
+        assert!(elapsed < STALL_TIME, "{elapsed:?}");
+    }
+
+    #[test]
+    fn stalled_camera_is_not_waited_for() {
+        let trigger = Arc::new(BatchTrigger::default());
+        let first = trigger.join();
+        // the second camera never adds a frame, e.g. a paused pipeline
+        let _second = trigger.join();
+
+        let start = Instant::now();
+        assert!(first.wait());
+        let elapsed = start.elapsed();
+        assert!(elapsed >= STALL_TIME, "{elapsed:?}");
+        assert!(elapsed < 3 * STALL_TIME, "{elapsed:?}");
+    }
+
+    #[test]
+    fn leaving_camera_releases_the_batch() {
+        let trigger = Arc::new(BatchTrigger::default());
+        let first = trigger.join();
+        let second = trigger.join();
+
+        let start = Instant::now();
+        let waiting = thread::spawn(move || (first.wait(), start.elapsed()));
+        thread::sleep(Duration::from_millis(50));
+        drop(second);
+
+        let (waited, elapsed) = waiting.join().unwrap();
+        assert!(waited);
+        assert!(elapsed < STALL_TIME, "{elapsed:?}");
+    }
+}
@@ -0,0 +1,47 @@
+//! Dispatches the messages of the pipeline bus to all parts of the application that are interested in them.
+//!
+//! Messages can only be popped from a bus once, so a single thread reads the bus and hands every message to the
+//! registered handlers.
+
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use gst::ClockTime;
+
+type MessageHandler = Box<dyn Fn(&gst::Message) + Send + Sync>;
+
+pub(crate) struct BusWatcher {
+    handlers: Mutex<Vec<MessageHandler>>,
+}
+
+impl BusWatcher {
+    /// Starts reading the bus of the pipeline.
+    pub(crate) fn start(bus: gst::Bus) -> Arc<Self> {
+        let watcher = Arc::new(BusWatcher {
+            handlers: Mutex::new(Vec::new()),
+        });
+
+        let bus_watcher = watcher.clone();
+        thread::spawn(move || {
+            for msg in bus.iter_timed(ClockTime::NONE) {
+                for handler in bus_watcher.handlers.lock().expect("Bus handler Mutex poisened").iter() {
+                    handler(&msg);
+                }
+            }
+        });
+
+        watcher
+    }
+
+    /// Registers a handler that is called with every message on the bus thread.
+    ///
+    /// Handlers must not register further handlers.
+    pub(crate) fn add_handler(&self, handler: impl Fn(&gst::Message) + Send + Sync + 'static) {
+        self.handlers
+            .lock()
+            .expect("Bus handler Mutex poisened")
+            .push(Box::new(handler));
+    }
+}
@@ -0,0 +1,128 @@
+//! Metrics of compression artifacts in decoded frames: blockiness at the 8×8 block grid of JPEG and H.264 and
+//! ringing next to strong edges.
+//!
+//! Raw sources give the baseline, a blockiness of about 1 and the ringing of the optics and the sensor.
+
+use ndarray::prelude::*;
+
+/// Size of the transform blocks of JPEG and of the H.264 8×8 transform.
+const BLOCK_SIZE: usize = 8;
+/// Minimum gradient of an edge pixel in DN.
+const EDGE_THRESHOLD: f32 = 30.0;
+/// Distance in pixels from an edge in which ringing is measured.
+const RINGING_DISTANCE: usize = 2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct CodecMetrics {
+    /// mean absolute difference across block boundaries relative to the mean absolute difference inside blocks
+    pub blockiness: f32,
+    /// mean absolute Laplacian in DN of the pixels next to strong edges
+    pub ringing: f32,
+}
+
+/// Calculates the codec metrics of a window stack whose top left pixel is at (x, y) in the frame.
+///
+/// Blockiness is averaged over the single frames, ringing is measured on the temporal mean so that the temporal noise
+/// does not count as ringing.
+pub(crate) fn calc_codec_metrics(win_stack: &Array3<f32>, x: usize, y: usize) -> CodecMetrics {
+    let n_frames = win_stack.shape()[2];
+    let blockiness = win_stack
+        .axis_iter(Axis(2))
+        .map(|frame| calc_blockiness(frame, x, y))
+        .sum::<f32>()
+        / n_frames.max(1) as f32;
+
+    let ringing = match win_stack.mean_axis(Axis(2)) {
+        Some(temporal_mean) => calc_ringing(temporal_mean.view()),
+        None => 0.0,
+    };
+
+    CodecMetrics { blockiness, ringing }
+}
+
+fn calc_blockiness(frame: ArrayView2<f32>, x: usize, y: usize) -> f32 {
+    let (h, w) = frame.dim();
+    let (mut boundary_sum, mut boundary_count) = (0.0, 0usize);
+    let (mut inner_sum, mut inner_count) = (0.0, 0usize);
+    let mut add = |diff: f32, is_boundary: bool| {
+        if is_boundary {
+            boundary_sum += diff;
+            boundary_count += 1;
+        } else {
+            inner_sum += diff;
+            inner_count += 1;
+        }
+    };
+
+    // differences between horizontal and vertical neighbors, on a boundary if the second pixel starts a block
+    for row in 0..h {
+        for col in 0..w.saturating_sub(1) {
+            let diff = (frame[[row, col + 1]] - frame[[row, col]]).abs();
+            add(diff, (x + col + 1).is_multiple_of(BLOCK_SIZE));
+        }
+    }
+    for row in 0..h.saturating_sub(1) {
+        for col in 0..w {
+            let diff = (frame[[row + 1, col]] - frame[[row, col]]).abs();
+            add(diff, (y + row + 1).is_multiple_of(BLOCK_SIZE));
+        }
+    }
+
+    if boundary_count == 0 || inner_count == 0 {
+        return 1.0;
+    }
+    let boundary_mean = boundary_sum / boundary_count as f32;
+    let inner_mean = inner_sum / inner_count as f32;
+    if inner_mean == 0.0 {
+        return if boundary_mean == 0.0 { 1.0 } else { f32::INFINITY };
+    }
+    boundary_mean / inner_mean
+}
+
+fn calc_ringing(frame: ArrayView2<f32>) -> f32 {
+    let (h, w) = frame.dim();
+    if h < 3 || w < 3 {
+        return 0.0;
+    }
+
+    let mut is_edge = Array2::<bool>::from_elem((h, w), false);
+    for row in 1..h - 1 {
+        for col in 1..w - 1 {
+            let gradient = (frame[[row, col + 1]] - frame[[row, col - 1]]).abs() / 2.0
+                + (frame[[row + 1, col]] - frame[[row - 1, col]]).abs() / 2.0;
+            is_edge[[row, col]] = gradient > EDGE_THRESHOLD;
+        }
+    }
+
+    let (mut sum, mut count) = (0.0, 0usize);
+    for row in 1..h - 1 {
+        for col in 1..w - 1 {
+            if is_edge[[row, col]] {
+                continue;
+            }
+            let near_edge = is_edge
+                .slice(s![
+                    row.saturating_sub(RINGING_DISTANCE)..(row + RINGING_DISTANCE + 1).min(h),
+                    col.saturating_sub(RINGING_DISTANCE)..(col + RINGING_DISTANCE + 1).min(w)
+                ])
+                .iter()
+                .any(|&edge| edge);
+            if !near_edge {
+                continue;
+            }
+            let laplacian = 4.0 * frame[[row, col]]
+                - frame[[row - 1, col]]
+                - frame[[row + 1, col]]
+                - frame[[row, col - 1]]
+                - frame[[row, col + 1]];
+            sum += laplacian.abs();
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f32
+    }
+}
@@ -0,0 +1,260 @@
+//! Settings of the application, restored on startup and saved when the application quits.
+//!
+//! The session state is stored as JSON in the XDG config directory. `--config <path>` starts with a named measurement
+//! profile instead. The profile itself is never overwritten, the session state is always saved to the default file.
+//! `--headless` measures once without a window and returns the verdict as exit code, see `App::verdict_exit_code`.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use slint::Model;
+use thiserror::Error;
+
+use crate::image_pipeline::{device_caps::RawSourceCaps, gstreamer::ImagePipeline, limits::Limits, units::Units};
+use crate::App;
+
+const CONFIG_ARG: &str = "--config";
+const HEADLESS_ARG: &str = "--headless";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Missing value of argument {0}")]
+    MissingValue(String),
+
+    #[error("Unknown argument: {0}")]
+    UnknownArgument(String),
+}
+
+/// Noise window in pixels of the video frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Roi {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl Roi {
+    /// Returns the window clamped to a frame of the given size, the window is not changed if the size is unknown.
+    pub(crate) fn clamped(self, width: i32, height: i32) -> Roi {
+        if width <= 0 || height <= 0 {
+            return self;
+        }
+        let x = self.x.clamp(0, width - 1);
+        let y = self.y.clamp(0, height - 1);
+        Roi {
+            x,
+            y,
+            w: self.w.clamp(1, width - x),
+            h: self.h.clamp(1, height - y),
+        }
+    }
+}
+
+/// Settings restored on startup, unset settings keep the defaults of the GUI.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// v4l2 device path of the main camera
+    pub device: Option<String>,
+    /// resolution of the main camera, e.g. "1920x1080"
+    pub resolution: Option<String>,
+    /// framerate of the main camera, e.g. "30/1"
+    pub framerate: Option<String>,
+    /// format the main camera delivers, "raw", "MJPEG" or "H.264"
+    pub format: Option<String>,
+    /// noise window of the main camera, set whenever playback starts or the source changes
+    pub roi: Option<Roi>,
+    /// noise windows of the additional cameras by device path
+    pub camera_rois: BTreeMap<String, Roi>,
+    /// name of the ROI preset of the main camera, the preset replaces `roi` once the first frame is shown
+    pub roi_preset: Option<String>,
+    pub number_of_frames: Option<i32>,
+    pub output_dir: Option<String>,
+    /// path of the measurement database, `VIDEO_NOISE_ANALYZER_DB` takes precedence
+    pub database: Option<String>,
+    /// pass/fail limits of the noise metrics, in DN whatever units are selected
+    pub limits: Limits,
+    /// units the noise metrics are shown, entered and exported in
+    pub units: Units,
+    /// path of the golden reference the measurements are compared with
+    pub golden_reference: Option<String>,
+    /// growth of a metric in percent above which it is flagged as regression
+    pub regression_tolerance: Option<f32>,
+    /// measure once without a window, set with `--headless` and never stored
+    #[serde(skip)]
+    pub headless: bool,
+}
+
+impl Settings {
+    /// Returns the path of the session state in the XDG config directory.
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("video-noise-analyzer")
+            .join("config.json")
+    }
+
+    /// Loads the profile given with `--config <path>`, otherwise the session state of the last run.
+    ///
+    /// A missing or invalid session state starts with the default settings, a missing or invalid profile is an
+    /// error.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, ConfigError> {
+        let mut profile = None;
+        let mut headless = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                CONFIG_ARG => profile = Some(args.next().ok_or(ConfigError::MissingValue(arg))?),
+                HEADLESS_ARG => headless = true,
+                _ => return Err(ConfigError::UnknownArgument(arg)),
+            }
+        }
+
+        let settings = match profile {
+            Some(profile) => {
+                let settings = Self::load(Path::new(&profile))?;
+                println!("Loaded measurement profile {profile}.");
+                settings
+            }
+            None => Self::restore(),
+        };
+        Ok(Settings { headless, ..settings })
+    }
+
+    // the session state of the last run, the defaults if there is none
+    fn restore() -> Self {
+        let path = Self::default_path();
+        if !path.exists() {
+            return Settings::default();
+        }
+        match Self::load(&path) {
+            Ok(settings) => {
+                println!("Restored settings from {}.", path.display());
+                settings
+            }
+            Err(e) => {
+                eprintln!("Could not restore settings from {}: {e}", path.display());
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Selects the stored capability of the main camera, the first capability stays selected if it is not available.
+    ///
+    /// The stored noise window belongs to the stored capability, it is dropped if the capability is not available.
+    pub(crate) fn select_capability(&mut self, caps: &mut RawSourceCaps) {
+        let Some(device) = &self.device else {
+            return;
+        };
+        let cap_idx = caps.get_caps().iter().position(|cap| {
+            cap.device_path() == device
+                && self
+                    .resolution
+                    .as_ref()
+                    .is_none_or(|r| *r == cap.resolution.to_string())
+                && self.format.as_ref().is_none_or(|f| *f == cap.format.to_string())
+        });
+        if cap_idx.is_none_or(|cap_idx| caps.set_resolution(cap_idx).is_err()) {
+            eprintln!("Stored capability of {device} not available, using the first capability.");
+            self.roi = None;
+            return;
+        }
+
+        let framerate_idx = self.framerate.as_ref().and_then(|framerate| {
+            caps.get_current_framerates_as_strings()
+                .iter()
+                .position(|f| f == framerate)
+        });
+        if let Some(framerate_idx) = framerate_idx {
+            let _ = caps.set_framerate(framerate_idx);
+        }
+    }
+}
+
+impl App {
+    /// Shows the restored settings in the GUI, the capability of the main camera is selected by the pipeline.
+    pub(crate) fn apply_settings(&self, settings: &Settings) {
+        if let Some(roi) = settings.roi {
+            self.set_default_noise_x(roi.x);
+            self.set_default_noise_y(roi.y);
+            self.set_default_noise_w(roi.w);
+            self.set_default_noise_h(roi.h);
+        }
+        if let Some(n_frames) = settings.number_of_frames {
+            self.set_number_of_frames(n_frames.max(1));
+        }
+        if let Some(output_dir) = &settings.output_dir {
+            self.set_output_dir(output_dir.into());
+        }
+    }
+
+    /// Returns the settings of the running session, settings that were not used, e.g. the noise windows of cameras
+    /// that were not opened, are taken from the restored settings.
+    pub fn session_settings(&self, image_pipeline: &ImagePipeline, restored: &Settings) -> Settings {
+        let mut settings = restored.clone();
+
+        let source = image_pipeline.source.lock().expect("Source Mutex poisened").clone();
+        let caps = image_pipeline.caps.lock().expect("Caps Mutex poisened");
+        if let Some(device) = source.v4l2_device_path(&caps).filter(|device| !device.is_empty()) {
+            settings.device = Some(device);
+            settings.resolution = Some(caps.get_current_resolution().to_string());
+            settings.framerate = caps
+                .get_current_framerate()
+                .ok()
+                .map(|framerate| format!("{}/{}", framerate.numer(), framerate.denom()));
+            settings.format = Some(caps.get_current_format().to_string());
+        }
+
+        // an empty noise window is not stored, e.g. after the window was cleared by a source switch
+        let roi = Roi {
+            x: self.get_noise_x(),
+            y: self.get_noise_y(),
+            w: self.get_noise_w(),
+            h: self.get_noise_h(),
+        };
+        if roi.w > 0 && roi.h > 0 {
+            settings.roi = Some(roi);
+        }
+        for tile in self.get_camera_tiles().iter() {
+            if tile.noise_w > 0 && tile.noise_h > 0 {
+                let roi = Roi {
+                    x: tile.noise_x,
+                    y: tile.noise_y,
+                    w: tile.noise_w,
+                    h: tile.noise_h,
+                };
+                settings.camera_rois.insert(tile.device.to_string(), roi);
+            }
+        }
+
+        settings.roi_preset = self.selected_roi_preset();
+        settings.number_of_frames = Some(self.get_number_of_frames());
+        settings.output_dir = Some(self.get_output_dir().to_string());
+        settings.limits = self.limits();
+        settings.units = self.units();
+        (settings.golden_reference, settings.regression_tolerance) = self.reference_settings();
+        settings
+    }
+}
@@ -1,4 +1,7 @@
-//! Contains functionality to query v4l2 video source device capabilities for raw video.
+//! Contains functionality to query v4l2 video source device capabilities for raw and compressed video.
+//!
+//! The device monitor keeps running after the initial query, so that devices plugged in or removed later are
+//! reported on its bus.
 
 use std::fmt::Display;
 
@@ -11,11 +14,56 @@ pub struct RawSourceCaps {
     curr_cap_idx: usize,
     curr_framerate_idx: usize,
     caps: Vec<RawSourceCap>,
+    monitor: gst::DeviceMonitor,
+    // set when the device of the selected capability was removed, until another capability is selected
+    active_device_removed: bool,
+}
+
+/// Format a v4l2 device delivers its frames in, compressed formats are decoded in the pipeline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CapFormat {
+    Raw,
+    Jpeg,
+    H264,
+}
+
+impl CapFormat {
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "video/x-raw" => Some(CapFormat::Raw),
+            "image/jpeg" => Some(CapFormat::Jpeg),
+            "video/x-h264" => Some(CapFormat::H264),
+            _ => None,
+        }
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            CapFormat::Raw => "video/x-raw",
+            CapFormat::Jpeg => "image/jpeg",
+            CapFormat::H264 => "video/x-h264",
+        }
+    }
+
+    pub fn is_compressed(self) -> bool {
+        self != CapFormat::Raw
+    }
+}
+
+impl Display for CapFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CapFormat::Raw => write!(f, "raw"),
+            CapFormat::Jpeg => write!(f, "MJPEG"),
+            CapFormat::H264 => write!(f, "H.264"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct RawSourceCap {
     pub resolution: Resolution,
+    pub format: CapFormat,
     framerates: Vec<gst::Fraction>,
     device_path: String,
 }
@@ -36,26 +84,43 @@ impl Display for RawSourceCap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "video/x-raw, Resolution: {}, Framerates: {:?}, Device Path: {}",
-            self.resolution, self.framerates, self.device_path,
+            "{}, Resolution: {}, Framerates: {:?}, Device Path: {}",
+            self.format.media_type(),
+            self.resolution,
+            self.framerates,
+            self.device_path,
         )
     }
 }
 
 impl RawSourceCap {
+    pub fn device_path(&self) -> &str {
+        &self.device_path
+    }
+
     pub fn to_string_wo_framerate(&self) -> String {
-        format!("{}: {}", self.device_path, self.resolution)
+        match self.format {
+            CapFormat::Raw => format!("{}: {}", self.device_path, self.resolution),
+            format => format!("{}: {} {}", self.device_path, self.resolution, format),
+        }
     }
 }
 
-/// Contains the v4l2 video source device capabilities for raw video available on the system.
+/// Contains the v4l2 video source device capabilities for raw and compressed video available on the system.
 impl RawSourceCaps {
     /// Creates a new RawSourceCaps by querying the system for available v4l2 video source devices.
     pub fn new() -> Result<Self, GstError> {
+        // device monitor to list video sources
+        let monitor = gst::DeviceMonitor::new();
+        monitor.add_filter(Some("Video/Source"), None);
+        monitor.start().expect("Could not start device monitor");
+
         let rsc = RawSourceCaps {
-            caps: Self::get_raw_source_caps(),
+            caps: monitor.devices().iter().flat_map(Self::get_device_caps).collect(),
             curr_cap_idx: 0,
             curr_framerate_idx: 0,
+            monitor,
+            active_device_removed: false,
         };
 
         if rsc.caps.is_empty() {
@@ -77,13 +142,24 @@ impl RawSourceCaps {
             return Err(GstError::InvalidValue);
         }
         self.curr_cap_idx = cap_idx;
+        self.active_device_removed = false;
         // reset frame rate to index 0 since it is possible that the new resolution has different available frame rates
         self.curr_framerate_idx = 0;
         Ok(())
     }
 
+    /// Selects the first capability of the device with the given path.
+    pub fn set_device(&mut self, device_path: &str) -> Result<(), GstError> {
+        let Some(cap_idx) = self.caps.iter().position(|cap| cap.device_path == device_path) else {
+            eprintln!("Unknown device {device_path}.");
+            return Err(GstError::InvalidValue);
+        };
+        self.set_resolution(cap_idx)
+    }
+
     pub fn set_framerate(&mut self, framerate_idx: usize) -> Result<(), GstError> {
-        if framerate_idx >= self.caps[self.curr_cap_idx].framerates.len() {
+        let n_framerates = self.caps.get(self.curr_cap_idx).map_or(0, |cap| cap.framerates.len());
+        if framerate_idx >= n_framerates {
             eprintln!("Invalid frame rate index.");
             return Err(GstError::InvalidValue);
         }
@@ -91,12 +167,38 @@ impl RawSourceCaps {
         Ok(())
     }
 
+    /// Returns the index of the currently selected capability.
+    pub fn get_current_cap_idx(&self) -> usize {
+        self.curr_cap_idx
+    }
+
+    /// Returns the paths of all devices with capabilities.
+    pub fn get_device_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = vec![];
+        for cap in &self.caps {
+            if !paths.contains(&cap.device_path) {
+                paths.push(cap.device_path.clone());
+            }
+        }
+        paths
+    }
+
+    /// Returns the index of the currently selected framerate.
+    pub fn get_current_framerate_idx(&self) -> usize {
+        self.curr_framerate_idx
+    }
+
+    /// Returns the device path of the selected capability, empty if all devices were removed.
     pub fn get_current_device_path(&self) -> &str {
-        self.caps.get(self.curr_cap_idx).unwrap().device_path.as_str()
+        self.caps
+            .get(self.curr_cap_idx)
+            .map_or("", |cap| cap.device_path.as_str())
     }
 
     pub fn get_current_framerates_as_strings(&self) -> Vec<String> {
-        let curr_cap = self.caps.get(self.curr_cap_idx).unwrap();
+        let Some(curr_cap) = self.caps.get(self.curr_cap_idx) else {
+            return vec![];
+        };
         curr_cap
             .framerates
             .iter()
@@ -118,79 +220,129 @@ impl RawSourceCaps {
         Ok(framerate.clone())
     }
 
-    /// Returns the currently active resolution.
+    /// Returns the format of the currently selected capability.
+    pub fn get_current_format(&self) -> CapFormat {
+        self.caps
+            .get(self.curr_cap_idx)
+            .map_or(CapFormat::Raw, |cap| cap.format)
+    }
+
+    /// Returns the currently active resolution, 0x0 if all devices were removed.
     pub fn get_current_resolution(&self) -> Resolution {
-        self.caps.get(self.curr_cap_idx).unwrap().resolution.clone()
+        self.caps
+            .get(self.curr_cap_idx)
+            .map_or(Resolution { width: 0, height: 0 }, |cap| cap.resolution.clone())
     }
 
-    /// Queries all available v4l2 video source devices and their raw capabilities.
-    fn get_raw_source_caps() -> Vec<RawSourceCap> {
+    /// Returns whether the device of the selected capability was removed since it was selected.
+    pub fn is_active_device_removed(&self) -> bool {
+        self.active_device_removed
+    }
+
+    /// Returns the bus of the device monitor, which receives the device added and removed messages.
+    pub fn get_monitor_bus(&self) -> gst::Bus {
+        self.monitor.bus()
+    }
+
+    /// Appends the capabilities of a plugged in device, returns false if the device is known or has no capabilities.
+    pub fn add_device(&mut self, device: &gst::Device) -> bool {
+        let device_caps = Self::get_device_caps(device);
+        let is_known = device_caps
+            .first()
+            .is_some_and(|cap| self.caps.iter().any(|c| c.device_path == cap.device_path));
+        if device_caps.is_empty() || is_known {
+            return false;
+        }
+        self.caps.extend(device_caps);
+        true
+    }
+
+    /// Removes the capabilities of a removed device.
+    ///
+    /// Returns None if the device was not known, otherwise whether it was the device of the selected capability.
+    /// The selection then moves to the first remaining capability.
+    pub fn remove_device(&mut self, device_path: &str) -> Option<bool> {
+        if !self.caps.iter().any(|cap| cap.device_path == device_path) {
+            return None;
+        }
+
+        let is_active = self.get_current_device_path() == device_path;
+        if is_active {
+            self.curr_cap_idx = 0;
+            self.curr_framerate_idx = 0;
+            self.active_device_removed = true;
+        } else {
+            // keep the selected capability selected
+            let removed_before = self.caps[..self.curr_cap_idx]
+                .iter()
+                .filter(|cap| cap.device_path == device_path)
+                .count();
+            self.curr_cap_idx -= removed_before;
+        }
+        self.caps.retain(|cap| cap.device_path != device_path);
+        Some(is_active)
+    }
+
+    /// Returns the raw, MJPEG and H.264 capabilities of a v4l2 video source device.
+    fn get_device_caps(device: &gst::Device) -> Vec<RawSourceCap> {
         let mut device_caps: Vec<RawSourceCap> = vec![];
 
-        // device monitor to list video sources
-        let monitor = gst::DeviceMonitor::new();
-        monitor.add_filter(Some("Video/Source"), None);
-        monitor.start().expect("Could not start device monitor");
+        let display_name = device.display_name();
+        let Some(device_path) = get_v4l2_device_path(device) else {
+            println!("Device {display_name} has no api.v4l2.path property.");
+            return device_caps;
+        };
 
-        for device in monitor.devices() {
-            let display_name = device.display_name();
-            // get linux video source device path, e.g. /dev/video0
-            let properties = match device.properties() {
-                None => {
-                    println!("Device {display_name} has no properties.");
+        if let Some(caps_list) = device.caps() {
+            println!("Available v4l2 caps of {device_path}:");
+            for caps in caps_list.iter() {
+                // high resolutions are often only offered compressed
+                let Some(format) = CapFormat::from_media_type(caps.name()) else {
                     continue;
-                }
-                Some(props) => props,
-            };
-            let device_path = match properties.get::<String>("api.v4l2.path") {
-                Err(_) => {
-                    println!("Device {display_name} has no api.v4l2.path property.");
+                };
+
+                let width = caps.get::<i32>("width");
+                let height = caps.get::<i32>("height");
+                // framerates are either a single fraction or a list of fractions, compressed formats may report a
+                // framerate range instead, which can not be selected
+                let framerates: Vec<gst::Fraction> = match caps.get::<gst::List>("framerate") {
+                    Ok(framerates) => framerates
+                        .iter()
+                        .filter_map(|f| f.get::<gst::Fraction>().ok())
+                        .collect(),
+                    Err(_) => caps.get::<gst::Fraction>("framerate").into_iter().collect(),
+                };
+                if framerates.is_empty() {
+                    println!("  Skipping {format} caps without discrete framerates: {caps:?}");
                     continue;
                 }
-                Ok(path) => path,
-            };
-
-            if let Some(caps_list) = device.caps() {
-                println!("Available v4l2 caps:");
-                for caps in caps_list.iter() {
-                    if caps.name() != "video/x-raw" {
-                        continue;
-                    }
-
-                    let width = caps.get::<i32>("width");
-                    let height = caps.get::<i32>("height");
-                    // framerates are either a single fraction or a list of fractions
-                    let framerates = caps.get::<gst::List>("framerate");
-                    let framerates: Vec<gst::Fraction> = if framerates.is_err() {
-                        vec![caps
-                            .get::<gst::Fraction>("framerate")
-                            .expect("No framerate fraction found.")]
-                    } else {
-                        framerates
-                            .unwrap()
-                            .iter()
-                            .map(|f| f.get::<gst::Fraction>().unwrap())
-                            .collect()
-                    };
 
-                    if let (Ok(width), Ok(height)) = (width, height) {
-                        let resolution = Resolution { width, height };
-                        println!("Resolution: {}, framerates: {:?}", &resolution, &framerates);
-                        let device_cap = RawSourceCap {
-                            resolution,
-                            framerates,
-                            device_path: device_path.clone(),
-                        };
-                        device_caps.push(device_cap);
-                    } else {
-                        println!("  Could not get width/height/framerate/image_format for caps: {caps:?}");
-                    }
+                if let (Ok(width), Ok(height)) = (width, height) {
+                    let resolution = Resolution { width, height };
+                    println!(
+                        "Format: {format}, resolution: {}, framerates: {:?}",
+                        &resolution, &framerates
+                    );
+                    let device_cap = RawSourceCap {
+                        resolution,
+                        format,
+                        framerates,
+                        device_path: device_path.clone(),
+                    };
+                    device_caps.push(device_cap);
+                } else {
+                    println!("  Could not get width/height/framerate/image_format for caps: {caps:?}");
                 }
-            } else {
-                println!("Device {display_name} has no caps.");
             }
+        } else {
+            println!("Device {display_name} has no caps.");
         }
 
         device_caps
     }
 }
+
+/// Returns the linux video source device path of the device, e.g. /dev/video0.
+pub fn get_v4l2_device_path(device: &gst::Device) -> Option<String> {
+    device.properties()?.get::<String>("api.v4l2.path").ok()
+}
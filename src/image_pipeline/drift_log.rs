@@ -0,0 +1,194 @@
+//! Long-duration logging of noise metrics and sensor temperature, e.g. to observe thermal drift.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::prelude::*;
+use gst::prelude::*;
+
+use crate::image_pipeline::{history::NoiseResult, source_switch::SourceSwitcher, v4l2_controls};
+
+const CSV_HEADER: &str = "timestamp,temperature_c,temporal_noise,fixed_pattern_noise,row_noise,column_noise,status";
+
+/// Where the sensor temperature is read from for each log entry.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TemperatureSource {
+    None,
+    V4l2Control { device_path: String, name: String },
+    File(PathBuf),
+}
+
+impl TemperatureSource {
+    /// Interprets the user input: empty means no temperature, an absolute path is read as a sysfs/hwmon
+    /// file and anything else is taken as the name of a v4l2 control of the given device.
+    pub(crate) fn parse(source: &str, device_path: &str) -> Self {
+        let source = source.trim();
+        if source.is_empty() {
+            TemperatureSource::None
+        } else if source.starts_with('/') {
+            TemperatureSource::File(PathBuf::from(source))
+        } else {
+            TemperatureSource::V4l2Control {
+                device_path: device_path.to_string(),
+                name: source.to_string(),
+            }
+        }
+    }
+
+    /// Reads the current temperature in degrees Celsius.
+    fn read(&self) -> Option<f32> {
+        match self {
+            TemperatureSource::None => None,
+            TemperatureSource::V4l2Control { device_path, name } => {
+                match v4l2_controls::read_control(device_path, name) {
+                    Ok(v) => Some(v as f32),
+                    Err(e) => {
+                        eprintln!("Could not read temperature control: {e}");
+                        None
+                    }
+                }
+            }
+            TemperatureSource::File(path) => {
+                let content = match fs::read_to_string(path) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        eprintln!("Could not read temperature file {path:?}: {e}");
+                        return None;
+                    }
+                };
+                let Ok(value) = content.trim().parse::<f32>() else {
+                    eprintln!("Temperature file {path:?} does not contain a number.");
+                    return None;
+                };
+                // sysfs thermal zones and hwmon sensors always report millidegrees Celsius
+                Some(value / 1000.0)
+            }
+        }
+    }
+}
+
+/// Appends a noise measurement to a CSV file once per interval.
+pub(crate) struct DriftLogger {
+    path: PathBuf,
+    interval: Duration,
+    temperature: TemperatureSource,
+    next_log: Instant,
+    last_result: Instant,
+    active: Arc<AtomicBool>,
+}
+
+impl Drop for DriftLogger {
+    fn drop(&mut self) {
+        self.active.store(false, Ordering::Relaxed);
+    }
+}
+
+impl DriftLogger {
+    /// Creates a new timestamped log file with CSV header in the given output directory.
+    pub(crate) fn new(output_dir: &Path, interval: Duration, temperature: TemperatureSource) -> io::Result<Self> {
+        fs::create_dir_all(output_dir)?;
+
+        let mut file_name = Local::now().format("%Y-%m-%d_%H:%M:%S").to_string();
+        file_name.push_str("_drift_log.csv");
+
+        let logger = DriftLogger {
+            path: output_dir.join(file_name),
+            interval,
+            temperature,
+            next_log: Instant::now(),
+            last_result: Instant::now(),
+            active: Arc::new(AtomicBool::new(true)),
+        };
+        logger.append(CSV_HEADER)?;
+
+        Ok(logger)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Logs the given result if the logging interval has elapsed since the last entry.
+    pub(crate) fn log(&mut self, result: &NoiseResult) {
+        self.last_result = Instant::now();
+        if self.last_result < self.next_log {
+            return;
+        }
+        self.next_log = self.last_result + self.interval;
+
+        let temperature = self.temperature.read().map(|t| t.to_string()).unwrap_or_default();
+        let line = format!(
+            "{},{},{},{},{},{},ok",
+            result.timestamp.to_rfc3339(),
+            temperature,
+            result.temporal,
+            result.fpn,
+            result.row,
+            result.col,
+        );
+        if let Err(e) = self.append(&line) {
+            // the next entry is tried anyway, e.g. if the output directory was temporarily unavailable
+            eprintln!("Could not write drift log entry to {:?}: {e}", self.path);
+        }
+    }
+
+    // opens the file for every entry so that the log survives unmounts and the file can be copied during the run
+    fn append(&self, line: &str) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{line}")
+    }
+
+    /// Restarts the pipeline if no noise result arrived for several logging intervals while it should be playing.
+    ///
+    /// The source is rebuilt by the switcher, so that the noise calculation discards the frames collected before the
+    /// restart.
+    pub(crate) fn start_watchdog(
+        logger: Arc<Mutex<Option<DriftLogger>>>,
+        pipeline: gst::Pipeline,
+        switcher: Arc<SourceSwitcher>,
+    ) {
+        let (active, interval) = match logger.lock().expect("Drift log Mutex poisened").as_ref() {
+            Some(l) => (l.active.clone(), l.interval),
+            None => return,
+        };
+        let timeout = (interval * 3).max(Duration::from_secs(30));
+
+        thread::spawn(move || {
+            while active.load(Ordering::Relaxed) {
+                thread::sleep(interval.min(Duration::from_secs(5)));
+
+                let mut guard = logger.lock().expect("Drift log Mutex poisened");
+                let Some(l) = guard.as_mut() else {
+                    break;
+                };
+                if !active.load(Ordering::Relaxed) {
+                    break;
+                }
+                if l.last_result.elapsed() < timeout || pipeline.current_state() != gst::State::Playing {
+                    continue;
+                }
+
+                eprintln!("No noise result for {timeout:?}, restarting pipeline.");
+                let line = format!("{},,,,,,pipeline restarted", Local::now().to_rfc3339());
+                if let Err(e) = l.append(&line) {
+                    eprintln!("Could not write drift log entry to {:?}: {e}", l.path);
+                }
+                l.last_result = Instant::now();
+                drop(guard);
+
+                if let Err(e) = switcher.restart() {
+                    eprintln!("Failed to restart pipeline: {e}");
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,500 @@
+//! Export of noise measurement results together with their measurement metadata as JSON or CSV report.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::image_pipeline::{
+    device_caps::RawSourceCaps,
+    frame_timing::FrameTimingStats,
+    history::NoiseResult,
+    limits::{Limits, Verdict},
+    source::{StreamInfo, VideoSource},
+    units::Units,
+    v4l2_controls::ControlCache,
+};
+
+// all CSV exports of an output directory are appended to this file to build up a dataset
+const CSV_FILE_NAME: &str = "noise_results.csv";
+const CSV_HEADER: &str = "timestamp,device_path,width,height,framerate,pixel_format,roi_x,roi_y,roi_w,roi_h,\
+frame_count,temporal_noise,fixed_pattern_noise,row_noise,column_noise,controls,effective_fps,interval_mean_ms,\
+interval_jitter_ms,interval_min_ms,interval_max_ms,interval_histogram,dropped_frames,duplicated_frames,skipped_frames,\
+verdict,limits,snr,defect_pixels,block_timeouts,units";
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+
+    #[error("Unknown export format: {0}")]
+    UnknownFormat(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl TryFrom<&str> for ExportFormat {
+    type Error = ExportError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(ExportError::UnknownFormat(value.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Roi {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Metrics {
+    pub temporal_noise: f32,
+    pub fixed_pattern_noise: f32,
+    pub row_noise: f32,
+    pub column_noise: f32,
+    /// mean level divided by the temporal noise, None without temporal noise or for results stored before the SNR
+    /// was calculated
+    #[serde(default)]
+    pub snr: Option<f32>,
+    /// hot and dead pixels of the noise window, None for results stored before the defect pixels were counted
+    #[serde(default)]
+    pub defect_pixels: Option<usize>,
+}
+
+impl From<&NoiseResult> for Metrics {
+    fn from(result: &NoiseResult) -> Self {
+        Metrics {
+            temporal_noise: result.temporal,
+            fixed_pattern_noise: result.fpn,
+            row_noise: result.row,
+            column_noise: result.col,
+            snr: result.snr,
+            defect_pixels: Some(result.defect_pixels),
+        }
+    }
+}
+
+impl Metrics {
+    // SNR and defect pixel count have no units
+    fn scaled(&self, factor: f32) -> Metrics {
+        Metrics {
+            temporal_noise: self.temporal_noise * factor,
+            fixed_pattern_noise: self.fixed_pattern_noise * factor,
+            row_noise: self.row_noise * factor,
+            column_noise: self.column_noise * factor,
+            ..self.clone()
+        }
+    }
+}
+
+/// A noise measurement result with all metadata needed to reproduce it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct MeasurementReport {
+    pub timestamp: String,
+    pub device_path: String,
+    pub width: i32,
+    pub height: i32,
+    pub framerate: String,
+    pub pixel_format: Option<String>,
+    pub roi: Roi,
+    pub frame_count: usize,
+    pub metrics: Metrics,
+    pub controls: BTreeMap<String, i64>,
+    // measurements stored before the frame timing was recorded have no timing
+    #[serde(default)]
+    pub timing: FrameTimingStats,
+    // None if no limit was set when the measurement was taken
+    #[serde(default)]
+    pub verdict: Option<Verdict>,
+    /// units of the noise metrics and their limits, reports stored before the units were selectable are in DN
+    #[serde(default)]
+    pub units: Units,
+}
+
+/// Metadata of the source a measurement was taken from, collected when its noise window stack is complete.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceMetadata {
+    pub device_path: String,
+    pub width: i32,
+    pub height: i32,
+    pub framerate: String,
+    pub pixel_format: Option<String>,
+    pub controls: BTreeMap<String, i64>,
+}
+
+impl SourceMetadata {
+    /// Collects the metadata of the current source.
+    ///
+    /// The negotiated stream format is preferred over the selected capability, v4l2 controls are only read from
+    /// v4l2 devices and taken from the cache while they are recent.
+    pub(crate) fn capture(
+        source: &VideoSource,
+        caps: &RawSourceCaps,
+        stream: Option<StreamInfo>,
+        control_cache: &mut ControlCache,
+    ) -> Self {
+        let device_path = source.name(caps);
+        let (width, height, framerate) = match &stream {
+            Some(info) => (info.width, info.height, info.framerate),
+            None => {
+                let resolution = caps.get_current_resolution();
+                let framerate = caps.get_current_framerate().unwrap_or(gst::Fraction::new(0, 1));
+                (resolution.width, resolution.height, framerate)
+            }
+        };
+
+        let controls = match source.v4l2_device_path(caps).map(|p| control_cache.controls(&p)) {
+            Some(Ok(controls)) => controls,
+            Some(Err(e)) => {
+                eprintln!("Could not read v4l2 controls of {device_path}: {e}");
+                BTreeMap::new()
+            }
+            None => BTreeMap::new(),
+        };
+
+        // frames of compressed v4l2 formats are measured after decoding
+        let pixel_format = stream.map(|info| match source {
+            VideoSource::V4l2 if caps.get_current_format().is_compressed() => {
+                format!("{} (decoded {})", info.format, caps.get_current_format())
+            }
+            _ => info.format,
+        });
+
+        SourceMetadata {
+            device_path,
+            width,
+            height,
+            framerate: format!("{}/{}", framerate.numer(), framerate.denom()),
+            pixel_format,
+            controls,
+        }
+    }
+}
+
+impl MeasurementReport {
+    /// Creates the report of the given result with the metadata of the source it was measured from, the metrics are
+    /// checked against the given limits.
+    pub(crate) fn new(result: &NoiseResult, limits: &Limits) -> Self {
+        let source = result.source.clone();
+        MeasurementReport {
+            timestamp: result.timestamp.to_rfc3339(),
+            device_path: source.device_path,
+            width: source.width,
+            height: source.height,
+            framerate: source.framerate,
+            pixel_format: source.pixel_format,
+            roi: Roi {
+                x: result.config.x,
+                y: result.config.y,
+                w: result.config.w,
+                h: result.config.h,
+            },
+            frame_count: result.config.n_frames,
+            metrics: Metrics::from(result),
+            controls: source.controls,
+            timing: result.timing,
+            verdict: limits.check(&Metrics::from(result)),
+            units: Units::Dn,
+        }
+    }
+
+    /// Returns the report with the noise metrics and their limits converted to the given units.
+    pub(crate) fn in_units(&self, units: Units) -> Self {
+        let factor = units.per_dn() / self.units.per_dn();
+        let mut report = self.clone();
+        report.metrics = self.metrics.scaled(factor);
+        if let Some(verdict) = &mut report.verdict {
+            verdict.limits = verdict.limits.scaled(factor);
+        }
+        report.units = units;
+        report
+    }
+
+    /// Writes the report into the output directory and returns the path of the written file.
+    ///
+    /// JSON reports are written to a new file per measurement, CSV reports are appended to a common file.
+    pub(crate) fn export(&self, output_dir: &Path, format: ExportFormat) -> Result<PathBuf, ExportError> {
+        fs::create_dir_all(output_dir)?;
+
+        match format {
+            ExportFormat::Json => {
+                let mut file_name = Local::now().format("%Y-%m-%d_%H:%M:%S%.3f").to_string();
+                file_name.push_str("_noise_report.json");
+                let path = output_dir.join(file_name);
+                self.write_json(&path)?;
+                Ok(path)
+            }
+            ExportFormat::Csv => {
+                let path = output_dir.join(CSV_FILE_NAME);
+                self.append_csv(&path)?;
+                Ok(path)
+            }
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> Result<String, ExportError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub(crate) fn write_json(&self, path: &Path) -> Result<(), ExportError> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Appends the report as a CSV row to the given file, writing the header first if the file is new.
+    ///
+    /// A file with other columns, e.g. written before a column was added, is moved aside with the current time as
+    /// prefix and a new file is started, so that the rows of a file always match its header.
+    pub(crate) fn append_csv(&self, path: &Path) -> Result<(), ExportError> {
+        let header = match File::open(path) {
+            Ok(file) => BufReader::new(file).lines().next().transpose()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let header = header.as_deref().map(str::trim_end);
+        if header.is_some_and(|header| header != CSV_HEADER) {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let moved = path.with_file_name(format!("{}_{file_name}", Local::now().format("%Y-%m-%d_%H:%M:%S%.3f")));
+            fs::rename(path, &moved)?;
+            println!(
+                "{} has other columns, moved it to {} and started a new file.",
+                path.display(),
+                moved.display()
+            );
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if header != Some(CSV_HEADER) {
+            writeln!(file, "{CSV_HEADER}")?;
+        }
+        writeln!(file, "{}", self.to_csv_row())?;
+        Ok(())
+    }
+
+    pub(crate) fn to_csv_row(&self) -> String {
+        // controls differ between devices, so they are stored in a single column to keep the columns stable
+        let controls = self
+            .controls
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        [
+            csv_field(&self.timestamp),
+            csv_field(&self.device_path),
+            self.width.to_string(),
+            self.height.to_string(),
+            csv_field(&self.framerate),
+            csv_field(self.pixel_format.as_deref().unwrap_or_default()),
+            self.roi.x.to_string(),
+            self.roi.y.to_string(),
+            self.roi.w.to_string(),
+            self.roi.h.to_string(),
+            self.frame_count.to_string(),
+            self.metrics.temporal_noise.to_string(),
+            self.metrics.fixed_pattern_noise.to_string(),
+            self.metrics.row_noise.to_string(),
+            self.metrics.column_noise.to_string(),
+            csv_field(&controls),
+            self.timing.effective_fps.to_string(),
+            self.timing.interval_mean_ms.to_string(),
+            self.timing.interval_jitter_ms.to_string(),
+            self.timing.interval_min_ms.to_string(),
+            self.timing.interval_max_ms.to_string(),
+            self.timing.histogram_string(),
+            self.timing.dropped_frames.to_string(),
+            self.timing.duplicated_frames.to_string(),
+            self.timing.skipped_frames.to_string(),
+            self.verdict
+                .as_ref()
+                .map(Verdict::label)
+                .unwrap_or_default()
+                .to_string(),
+            csv_field(
+                &self
+                    .verdict
+                    .as_ref()
+                    .map(|verdict| verdict.limits.to_compact_string())
+                    .unwrap_or_default(),
+            ),
+            self.metrics.snr.map(|snr| snr.to_string()).unwrap_or_default(),
+            self.metrics
+                .defect_pixels
+                .map(|defect_pixels| defect_pixels.to_string())
+                .unwrap_or_default(),
+            self.timing.block_timeouts.to_string(),
+            self.units.to_string(),
+        ]
+        .join(",")
+    }
+}
+
+/// Quotes a CSV field if it contains separators, quotes or line breaks.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> MeasurementReport {
+        let metrics = Metrics {
+            temporal_noise: 2.55,
+            fixed_pattern_noise: 5.1,
+            row_noise: 0.0,
+            column_noise: 25.5,
+            snr: Some(40.0),
+            defect_pixels: Some(3),
+        };
+        let limits = Limits {
+            temporal_noise: Some(2.55),
+            snr: Some(30.0),
+            ..Default::default()
+        };
+        MeasurementReport {
+            timestamp: "2026-10-18T12:00:00+02:00".to_string(),
+            device_path: "/dev/video0".to_string(),
+            width: 640,
+            height: 480,
+            framerate: "30/1".to_string(),
+            pixel_format: Some("YUY2".to_string()),
+            roi: Roi { x: 0, y: 0, w: 4, h: 4 },
+            frame_count: 100,
+            verdict: limits.check(&metrics),
+            metrics,
+            controls: Default::default(),
+            timing: Default::default(),
+            units: Units::Dn,
+        }
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 1e-4, "{value} != {expected}");
+    }
+
+    #[test]
+    fn noise_metrics_and_limits_are_converted() {
+        let percent = report().in_units(Units::PercentFullScale);
+        assert_eq!(percent.units, Units::PercentFullScale);
+        assert_close(percent.metrics.temporal_noise, 1.0);
+        assert_close(percent.metrics.fixed_pattern_noise, 2.0);
+        assert_close(percent.metrics.column_noise, 10.0);
+        assert_eq!(percent.metrics.snr, Some(40.0));
+        assert_eq!(percent.metrics.defect_pixels, Some(3));
+
+        let verdict = percent.verdict.as_ref().unwrap();
+        assert!(verdict.passed);
+        assert_close(verdict.limits.temporal_noise.unwrap(), 1.0);
+        assert_eq!(verdict.limits.snr, Some(30.0));
+        assert!(percent.to_csv_row().ends_with(",% FS"));
+
+        // converting back to DN restores the measured values
+        let dn = percent.in_units(Units::Dn);
+        assert_close(dn.metrics.column_noise, 25.5);
+        assert!(dn.to_csv_row().ends_with(",DN"));
+    }
+
+    #[test]
+    fn reports_without_units_are_in_dn() {
+        let mut json: serde_json::Value = serde_json::from_str(&report().to_json().unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("units");
+        let report: MeasurementReport = serde_json::from_value(json).unwrap();
+        assert_eq!(report.units, Units::Dn);
+    }
+
+    // an empty directory per test, tests run in parallel
+    fn output_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("noise_export_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn csv_files(dir: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "csv"))
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn csv_rows_are_appended_below_one_header() {
+        let dir = output_dir("append");
+        let path = report().export(&dir, ExportFormat::Csv).unwrap();
+        report().export(&dir, ExportFormat::Csv).unwrap();
+
+        let csv = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], lines[2]);
+        assert_eq!(lines[1].split(',').count(), CSV_HEADER.split(',').count());
+        assert_eq!(csv_files(&dir), vec![path]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn csv_with_other_columns_is_moved_aside() {
+        let dir = output_dir("other_columns");
+        let path = dir.join(CSV_FILE_NAME);
+        let old = "timestamp,device_path,temporal_noise\n2026-01-01T00:00:00+00:00,/dev/video0,1.5\n";
+        fs::write(&path, old).unwrap();
+
+        assert_eq!(report().export(&dir, ExportFormat::Csv).unwrap(), path);
+
+        let csv = fs::read_to_string(&path).unwrap();
+        assert_eq!(csv.lines().next(), Some(CSV_HEADER));
+        assert_eq!(csv.lines().count(), 2);
+        let files = csv_files(&dir);
+        assert_eq!(files.len(), 2);
+        let moved = files.iter().find(|file| **file != path).unwrap();
+        assert!(moved.to_string_lossy().ends_with(&format!("_{CSV_FILE_NAME}")));
+        assert_eq!(fs::read_to_string(moved).unwrap(), old);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn empty_csv_gets_a_header() {
+        let dir = output_dir("empty");
+        let path = dir.join(CSV_FILE_NAME);
+        fs::write(&path, "").unwrap();
+
+        report().append_csv(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().next(), Some(CSV_HEADER));
+        assert_eq!(csv_files(&dir), vec![path]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
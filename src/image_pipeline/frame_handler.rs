@@ -2,37 +2,104 @@ use std::{
     fs,
     path::{Path, PathBuf},
     sync::{
-        mpsc::{self, Receiver},
-        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        Arc,
     },
 };
 
 use chrono::prelude::*;
-use gst::{glib::object::Cast, prelude::GstBinExt};
+use gst::{
+    glib::object::Cast,
+    prelude::{ElementExt, GstBinExt},
+};
 use image::RgbImage;
 use slint::{Image, Rgb8Pixel, SharedPixelBuffer, Weak};
 
-use crate::App;
+use crate::{
+    image_pipeline::{
+        frame_queue::{FrameQueue, QueuePolicy, FRAME_QUEUE_CAPACITY},
+        frame_timing::FrameTimestamp,
+    },
+    App,
+};
+
+// buffers the appsink holds for the callback, older buffers are dropped unless the queue policy blocks
+const APPSINK_MAX_BUFFERS: u32 = 2;
+
+/// A converted RGB frame together with the timestamps of its buffer.
+///
+/// The buffer is shared with GStreamer and only mapped when its pixels are read, the rows may be padded to the stride
+/// given by the video info.
+pub struct Frame {
+    pub buffer: gst::Buffer,
+    pub info: gst_video::VideoInfo,
+    pub timestamp: FrameTimestamp,
+}
+
+impl Frame {
+    /// Copies the frame into a pixel buffer for the GUI, without the padding of the rows.
+    fn to_pixel_buffer(&self) -> Option<SharedPixelBuffer<Rgb8Pixel>> {
+        let map = self.buffer.map_readable().ok()?;
+        let data = map.as_slice();
+        let (width, height) = (self.info.width(), self.info.height());
+        let stride = self.info.stride()[0] as usize;
+        let row_len = width as usize * 3;
+        if stride == row_len {
+            let data = data.get(..row_len * height as usize)?;
+            return Some(SharedPixelBuffer::<Rgb8Pixel>::clone_from_slice(data, width, height));
+        }
+
+        let mut pixel_buffer = SharedPixelBuffer::<Rgb8Pixel>::new(width, height);
+        for (row, dst) in pixel_buffer.make_mut_bytes().chunks_exact_mut(row_len).enumerate() {
+            dst.copy_from_slice(data.get(row * stride..row * stride + row_len)?);
+        }
+        Some(pixel_buffer)
+    }
+
+    /// Copies the frame into an RGB image, e.g. as snapshot of a measurement for its report.
+    pub(crate) fn to_rgb_image(&self) -> Option<RgbImage> {
+        let pixel_buffer = self.to_pixel_buffer()?;
+        RgbImage::from_raw(
+            pixel_buffer.width(),
+            pixel_buffer.height(),
+            pixel_buffer.as_bytes().to_vec(),
+        )
+    }
+}
 
 #[derive(Clone)]
 pub struct FrameHandler {
     cat: Image,
     ui: Arc<Weak<App>>,
-    pub frame_rx: Arc<Mutex<Receiver<SharedPixelBuffer<Rgb8Pixel>>>>,
+    pub frame_queue: Arc<FrameQueue>,
+    // id of the camera tile showing the frames, None for the main video window
+    camera: Option<i32>,
 }
 
 // handles the display of frames in the GUI window
 impl FrameHandler {
     pub fn init(pipeline: &gst::Pipeline, ui: Weak<App>) -> Self {
+        Self::init_display(pipeline, ui, None)
+    }
+
+    /// Shows the frames in the tile of an additional camera instead of the main video window.
+    pub(crate) fn init_camera(pipeline: &gst::Pipeline, ui: Weak<App>, camera: i32) -> Self {
+        Self::init_display(pipeline, ui, Some(camera))
+    }
+
+    fn init_display(pipeline: &gst::Pipeline, ui: Weak<App>, camera: Option<i32>) -> Self {
         // set up link of image pipeline output frames to GUI
-        let new_frame_callback: fn(App, Image) = |ui, new_frame| {
-            if ui.get_playing() {
-                ui.set_video_frame(new_frame);
+        let new_frame_callback = move |ui: App, new_frame: Image| match camera {
+            None => {
+                if ui.get_playing() {
+                    ui.set_video_frame(new_frame);
+                }
             }
+            Some(id) => ui.update_camera_tile(id, |tile| tile.frame = new_frame),
         };
 
         let ui = Arc::new(ui);
-        let rx = Self::register_frame_callback(pipeline, ui.clone(), new_frame_callback)
+        let frame_queue = Self::register_frame_callback(pipeline, ui.clone(), new_frame_callback)
             .expect("Failed to register new frame callback");
 
         // load pause image (cat)
@@ -44,16 +111,18 @@ impl FrameHandler {
         FrameHandler {
             cat,
             ui,
-            frame_rx: Arc::new(Mutex::new(rx)),
+            frame_queue,
+            camera,
         }
     }
 
     pub fn display_pause_image(&self) {
         println!("Catting the window!");
-        self.ui
-            .upgrade()
-            .expect("Could not upgrade UI.")
-            .set_video_frame(self.cat.clone());
+        let ui = self.ui.upgrade().expect("Could not upgrade UI.");
+        match self.camera {
+            None => ui.set_video_frame(self.cat.clone()),
+            Some(id) => ui.update_camera_tile(id, |tile| tile.frame = self.cat.clone()),
+        }
     }
 
     pub fn take_screenshot(&self) {
@@ -87,18 +156,32 @@ impl FrameHandler {
         println!("Screenshot successfully saved to {output_name:?}.");
     }
 
+    /// Sets the policy of the frame queue, the appsink only drops buffers if the queue does not block.
+    pub fn set_queue_policy(&self, pipeline: &gst::Pipeline, policy: QueuePolicy) {
+        self.frame_queue.set_policy(policy);
+        match get_appsink(pipeline) {
+            Some(sink) => sink.set_drop(policy != QueuePolicy::Block),
+            None => eprintln!("Could not find appsink element in pipeline."),
+        }
+        println!("Frame queue policy: {policy}.");
+    }
+
     fn register_frame_callback<AppHandle: slint::ComponentHandle + 'static>(
         pipeline: &gst::Pipeline,
         ui: Arc<Weak<AppHandle>>,
-        new_frame_cb: fn(AppHandle, Image),
-    ) -> Result<Receiver<SharedPixelBuffer<Rgb8Pixel>>, ()> {
-        let sink = pipeline
-            .by_name("appsink0")
-            .expect("Could not find appsink element in pipeline.")
-            .downcast::<gst_app::AppSink>()
-            .expect("Could not downcast Element to AppSink.");
-
-        let (tx, rx) = mpsc::channel::<SharedPixelBuffer<Rgb8Pixel>>();
+        new_frame_cb: impl Fn(AppHandle, Image) + Send + Sync + 'static,
+    ) -> Result<Arc<FrameQueue>, ()> {
+        let new_frame_cb = Arc::new(new_frame_cb);
+        let sink = get_appsink(pipeline).expect("Could not find appsink element in pipeline.");
+
+        let policy = QueuePolicy::default();
+        let frame_queue = Arc::new(FrameQueue::new(FRAME_QUEUE_CAPACITY, policy));
+        sink.set_max_buffers(APPSINK_MAX_BUFFERS);
+        sink.set_drop(policy != QueuePolicy::Block);
+
+        // a frame is only handed to the event loop once the previous frame was displayed
+        let display_pending = Arc::new(AtomicBool::new(false));
+        let queue = frame_queue.clone();
         sink.set_callbacks(
             gst_app::AppSinkCallbacks::builder()
                 .new_sample(move |appsink| {
@@ -107,40 +190,75 @@ impl FrameHandler {
                         return Err(gst::FlowError::Eos);
                     };
 
-                    let Some(buffer) = sample.buffer() else {
+                    let Some(buffer) = sample.buffer_owned() else {
                         println!("Sample has no buffer.");
                         return Err(gst::FlowError::Error);
                     };
-                    let Ok(map) = buffer.map_readable() else {
-                        println!("Failed to map buffer readable.");
-                        return Err(gst::FlowError::Error);
+
+                    let Some(video_info) = sample
+                        .caps()
+                        .and_then(|caps| gst_video::VideoInfo::from_caps(caps).ok())
+                    else {
+                        eprintln!("Sample has no valid video caps.");
+                        return Err(gst::FlowError::NotNegotiated);
+                    };
+                    // the offset is the sequence number of the frame if the source counts its frames
+                    let fps = video_info.fps();
+                    let timestamp = FrameTimestamp {
+                        pts: buffer.pts(),
+                        offset: (buffer.offset() != gst::BUFFER_OFFSET_NONE).then_some(buffer.offset()),
+                        nominal_interval: (fps.numer() > 0).then(|| {
+                            gst::ClockTime::from_nseconds(1_000_000_000 * fps.denom() as u64 / fps.numer() as u64)
+                        }),
+                        capture_time: appsink.base_time().zip(buffer.pts()).map(|(base, pts)| base + pts),
                     };
-                    let data = map.as_slice();
 
-                    // Create an Image from the raw RGB data
-                    let video_info = gst_video::VideoInfo::from_caps(sample.caps().unwrap()).unwrap();
-                    let pixel_buffer =
-                        SharedPixelBuffer::<Rgb8Pixel>::clone_from_slice(data, video_info.width(), video_info.height());
+                    let frame = Frame {
+                        buffer,
+                        info: video_info,
+                        timestamp,
+                    };
 
-                    let buf_clone = pixel_buffer.clone();
-                    ui.upgrade_in_event_loop(move |ui| {
-                        new_frame_cb(ui, Image::from_rgb8(buf_clone));
-                    })
-                    .expect("Could not upgrade UI in event loop.");
+                    // only displayed frames are copied, the noise calculation reads the buffer itself
+                    if !display_pending.swap(true, Ordering::AcqRel) {
+                        match frame.to_pixel_buffer() {
+                            Some(pixel_buffer) => {
+                                let display_pending = display_pending.clone();
+                                let new_frame_cb = new_frame_cb.clone();
+                                let displayed = ui.upgrade_in_event_loop(move |ui| {
+                                    new_frame_cb(ui, Image::from_rgb8(pixel_buffer));
+                                    display_pending.store(false, Ordering::Release);
+                                });
+                                // the event loop has quit, the application is shutting down
+                                if displayed.is_err() {
+                                    return Err(gst::FlowError::Flushing);
+                                }
+                            }
+                            // a frame that can not be displayed must not stop the stream, the next frame is displayed
+                            // and the noise calculation reports the frame if it can not read it either
+                            None => {
+                                eprintln!("Failed to map buffer readable, frame not displayed.");
+                                display_pending.store(false, Ordering::Release);
+                            }
+                        }
+                    }
 
-                    // send signal that new frame is ready
-                    tx.send(pixel_buffer).expect("Sending new frame signal failed.");
+                    queue.push(frame);
 
                     Ok(gst::FlowSuccess::Ok)
                 })
                 .build(),
         );
-        Ok(rx)
+        Ok(frame_queue)
     }
 }
 
+fn get_appsink(pipeline: &gst::Pipeline) -> Option<gst_app::AppSink> {
+    pipeline.by_name("sink")?.downcast::<gst_app::AppSink>().ok()
+}
+
 #[inline]
-fn get_frame_as_rgbimage(ui: &App) -> Option<RgbImage> {
+pub(crate) fn get_frame_as_rgbimage(ui: &App) -> Option<RgbImage> {
     let frame = ui.get_video_frame();
     let Some(frame_buf) = frame.to_rgb8() else {
         eprintln!("Could not obtain pixel buffer from video frame.");
@@ -0,0 +1,304 @@
+//! Bounded queue of the frames handed from the appsink callback to the noise calculation.
+//!
+//! If the noise calculation is slower than the source, the policy decides whether the streaming thread waits for free
+//! space, which slows down the whole pipeline, or whether frames are dropped. Dropped frames are counted, so that
+//! measurements with skipped frames can be flagged, frames the block policy dropped after its timeout separately.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::image_pipeline::frame_handler::Frame;
+
+/// Number of frames the queue holds before the policy applies.
+pub const FRAME_QUEUE_CAPACITY: usize = 4;
+/// Longest time the streaming thread waits for free space before the oldest frame is dropped anyway, so that a
+/// stalled noise calculation can not stall the pipeline, e.g. while it is stopped for a source switch.
+const MAX_BLOCK_TIME: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Error)]
+#[error("Unknown frame queue policy: {0}")]
+pub struct UnknownPolicy(String);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum QueuePolicy {
+    /// the streaming thread waits until the noise calculation took a frame, but at most `MAX_BLOCK_TIME`, then the
+    /// oldest frame is dropped
+    Block,
+    /// the oldest queued frame is dropped, the noise calculation sees the most recent frames
+    #[default]
+    DropOldest,
+    /// the new frame is dropped, the queued frames stay consecutive
+    DropNewest,
+}
+
+impl Display for QueuePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueuePolicy::Block => write!(f, "block up to {} s", MAX_BLOCK_TIME.as_secs()),
+            QueuePolicy::DropOldest => write!(f, "drop oldest"),
+            QueuePolicy::DropNewest => write!(f, "drop newest"),
+        }
+    }
+}
+
+impl TryFrom<&str> for QueuePolicy {
+    type Error = UnknownPolicy;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().replace(['-', '_'], " ").as_str() {
+            "block" => Ok(QueuePolicy::Block),
+            "drop oldest" => Ok(QueuePolicy::DropOldest),
+            "drop newest" => Ok(QueuePolicy::DropNewest),
+            // the GUI shows the longest blocking time
+            name if name == QueuePolicy::Block.to_string() => Ok(QueuePolicy::Block),
+            _ => Err(UnknownPolicy(value.to_string())),
+        }
+    }
+}
+
+pub struct FrameQueue {
+    frames: Mutex<VecDeque<Frame>>,
+    policy: Mutex<QueuePolicy>,
+    capacity: usize,
+    not_empty: Condvar,
+    not_full: Condvar,
+    dropped: AtomicU64,
+    block_timeouts: AtomicU64,
+    closed: AtomicBool,
+}
+
+impl FrameQueue {
+    pub(crate) fn new(capacity: usize, policy: QueuePolicy) -> Self {
+        FrameQueue {
+            frames: Mutex::new(VecDeque::with_capacity(capacity)),
+            policy: Mutex::new(policy),
+            capacity: capacity.max(1),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            dropped: AtomicU64::new(0),
+            block_timeouts: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn policy(&self) -> QueuePolicy {
+        *self.policy.lock().expect("Queue policy Mutex poisened")
+    }
+
+    pub(crate) fn set_policy(&self, policy: QueuePolicy) {
+        *self.policy.lock().expect("Queue policy Mutex poisened") = policy;
+        // frames waiting for space are pushed according to the new policy
+        self.not_full.notify_all();
+    }
+
+    /// Appends a frame according to the policy, returns false if a frame was dropped.
+    pub(crate) fn push(&self, frame: Frame) -> bool {
+        let mut frames = self.frames.lock().expect("Frame queue Mutex poisened");
+        let deadline = Instant::now() + MAX_BLOCK_TIME;
+        while frames.len() >= self.capacity && self.policy() == QueuePolicy::Block {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                break;
+            }
+            frames = self
+                .not_full
+                .wait_timeout(frames, timeout)
+                .expect("Frame queue Mutex poisened")
+                .0;
+        }
+
+        let mut pushed = true;
+        if frames.len() >= self.capacity {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            pushed = false;
+            match self.policy() {
+                QueuePolicy::DropNewest => return false,
+                QueuePolicy::DropOldest => {
+                    frames.pop_front();
+                }
+                QueuePolicy::Block => {
+                    self.block_timeouts.fetch_add(1, Ordering::Relaxed);
+                    frames.pop_front();
+                }
+            }
+        }
+        frames.push_back(frame);
+        self.not_empty.notify_one();
+        pushed
+    }
+
+    /// Waits for the next frame, returns None once the queue is closed.
+    pub(crate) fn pop(&self) -> Option<Frame> {
+        let mut frames = self.frames.lock().expect("Frame queue Mutex poisened");
+        loop {
+            if self.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            if let Some(frame) = frames.pop_front() {
+                self.not_full.notify_one();
+                return Some(frame);
+            }
+            frames = self.not_empty.wait(frames).expect("Frame queue Mutex poisened");
+        }
+    }
+
+    /// Discards the queued frames, e.g. frames that arrived before a batch was triggered.
+    pub(crate) fn clear(&self) {
+        self.frames.lock().expect("Frame queue Mutex poisened").clear();
+        self.not_full.notify_all();
+    }
+
+    /// Wakes up and ends the consumer of the queue, e.g. when its camera is removed.
+    pub(crate) fn close(&self) {
+        // the flag is set while holding the lock, so that a consumer can not miss the wake up
+        let _frames = self.frames.lock().expect("Frame queue Mutex poisened");
+        self.closed.store(true, Ordering::Release);
+        self.not_empty.notify_all();
+    }
+
+    pub(crate) fn queued(&self) -> usize {
+        self.frames.lock().expect("Frame queue Mutex poisened").len()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of frames dropped since the queue was created.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of frames the block policy dropped after waiting `MAX_BLOCK_TIME` since the queue was
+    /// created, they are included in `dropped`.
+    pub(crate) fn block_timeouts(&self) -> u64 {
+        self.block_timeouts.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+    use crate::image_pipeline::frame_timing::FrameTimestamp;
+
+    // frames are told apart by their sequence number
+    fn frame(idx: u64) -> Frame {
+        gst::init().expect("Could not initialize GStreamer.");
+        Frame {
+            buffer: gst::Buffer::new(),
+            info: gst_video::VideoInfo::builder(gst_video::VideoFormat::Rgb, 2, 2)
+                .build()
+                .expect("Could not create video info."),
+            timestamp: FrameTimestamp {
+                offset: Some(idx),
+                ..FrameTimestamp::default()
+            },
+        }
+    }
+
+    fn fill(queue: &FrameQueue, n: u64) -> Vec<bool> {
+        (0..n).map(|idx| queue.push(frame(idx))).collect()
+    }
+
+    fn drain(queue: &FrameQueue) -> Vec<u64> {
+        let mut frames = Vec::new();
+        while queue.queued() > 0 {
+            let frame = queue.pop().expect("Queue closed");
+            frames.push(frame.timestamp.offset.expect("Frame without sequence number"));
+        }
+        frames
+    }
+
+    #[test]
+    fn policy_names() {
+        for policy in [QueuePolicy::Block, QueuePolicy::DropOldest, QueuePolicy::DropNewest] {
+            assert_eq!(QueuePolicy::try_from(policy.to_string().as_str()).unwrap(), policy);
+        }
+        assert_eq!(QueuePolicy::try_from("drop-oldest").unwrap(), QueuePolicy::DropOldest);
+        assert!(QueuePolicy::try_from("drop all").is_err());
+    }
+
+    #[test]
+    fn drop_oldest() {
+        let queue = FrameQueue::new(2, QueuePolicy::DropOldest);
+        assert_eq!(fill(&queue, 4), [true, true, false, false]);
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(queue.block_timeouts(), 0);
+        assert_eq!(drain(&queue), [2, 3]);
+    }
+
+    #[test]
+    fn drop_newest() {
+        let queue = FrameQueue::new(2, QueuePolicy::DropNewest);
+        assert_eq!(fill(&queue, 4), [true, true, false, false]);
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(drain(&queue), [0, 1]);
+    }
+
+    #[test]
+    fn block_waits_for_consumer() {
+        let queue = Arc::new(FrameQueue::new(2, QueuePolicy::Block));
+        assert_eq!(fill(&queue, 2), [true, true]);
+
+        let consumer = thread::spawn({
+            let queue = queue.clone();
+            move || {
+                thread::sleep(Duration::from_millis(100));
+                queue.pop().map(|frame| frame.timestamp.offset)
+            }
+        });
+        let start = Instant::now();
+        assert!(queue.push(frame(2)));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(consumer.join().unwrap(), Some(Some(0)));
+        assert_eq!(queue.dropped(), 0);
+        assert_eq!(queue.block_timeouts(), 0);
+        assert_eq!(drain(&queue), [1, 2]);
+    }
+
+    #[test]
+    fn block_drops_oldest_after_timeout() {
+        let queue = FrameQueue::new(2, QueuePolicy::Block);
+        assert_eq!(fill(&queue, 2), [true, true]);
+
+        let start = Instant::now();
+        assert!(!queue.push(frame(2)));
+        assert!(start.elapsed() >= MAX_BLOCK_TIME);
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(queue.block_timeouts(), 1);
+        assert_eq!(drain(&queue), [1, 2]);
+    }
+
+    #[test]
+    fn dropped_counts_since_creation() {
+        let queue = FrameQueue::new(1, QueuePolicy::DropNewest);
+        assert_eq!(fill(&queue, 3), [true, false, false]);
+        queue.clear();
+        queue.set_policy(QueuePolicy::DropOldest);
+        assert_eq!(fill(&queue, 2), [true, false]);
+        assert_eq!(queue.dropped(), 3);
+    }
+
+    #[test]
+    fn close_ends_consumer() {
+        let queue = Arc::new(FrameQueue::new(2, QueuePolicy::DropOldest));
+        let consumer = thread::spawn({
+            let queue = queue.clone();
+            move || queue.pop().is_none()
+        });
+        thread::sleep(Duration::from_millis(50));
+        queue.close();
+        assert!(consumer.join().unwrap());
+    }
+}
@@ -0,0 +1,261 @@
+//! Timing of the frames entering a noise window stack: effective framerate, jitter of the frame intervals and
+//! dropped or duplicated frames.
+//!
+//! A missed frame stretches the time a window stack covers and a duplicated frame lowers the temporal noise, so the
+//! timing is reported with every measurement. Drops are counted from gaps in the buffer offsets, which v4l2src sets to
+//! the frame sequence number. Sources without offsets are checked for frame intervals much longer than the nominal
+//! interval instead.
+
+use gst::ClockTime;
+use serde::{Deserialize, Serialize};
+
+/// Number of bins of the frame interval histogram.
+const HISTOGRAM_BINS: usize = 8;
+/// Width of a histogram bin relative to the nominal frame interval, the last bin counts all longer intervals.
+const HISTOGRAM_BIN_WIDTH: f64 = 0.25;
+/// Intervals longer than this multiple of the nominal interval count as dropped frames if there are no offsets.
+const DROP_FACTOR: f64 = 1.5;
+
+/// Timestamps of a single buffer as delivered by the appsink.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameTimestamp {
+    pub pts: Option<ClockTime>,
+    /// sequence number of the frame, None if the source does not number its buffers
+    pub offset: Option<u64>,
+    /// frame interval of the negotiated framerate, None for variable framerates
+    pub nominal_interval: Option<ClockTime>,
+    /// clock time of the frame, i.e. its running time plus the base time of the pipeline. All pipelines use the
+    /// monotonic system clock, so the capture times of different pipelines can be compared.
+    pub capture_time: Option<ClockTime>,
+}
+
+/// Frame timing of one noise window stack.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FrameTimingStats {
+    pub effective_fps: f64,
+    pub interval_mean_ms: f64,
+    /// standard deviation of the frame intervals
+    pub interval_jitter_ms: f64,
+    pub interval_min_ms: f64,
+    pub interval_max_ms: f64,
+    /// frame intervals in quarters of the nominal interval, of the mean interval for variable framerates
+    pub interval_histogram: [u64; HISTOGRAM_BINS],
+    pub dropped_frames: u64,
+    pub duplicated_frames: u64,
+    /// frames dropped by the frame queue because the noise calculation was too slow, also counted as dropped frames
+    /// if the source numbers its frames
+    #[serde(default)]
+    pub skipped_frames: u64,
+    /// skipped frames the block policy dropped after its timeout, a measurement under the block policy is expected to
+    /// have none
+    #[serde(default)]
+    pub block_timeouts: u64,
+}
+
+impl FrameTimingStats {
+    /// Returns the histogram as bin counts separated by `|`, e.g. for the GUI and CSV exports.
+    pub(crate) fn histogram_string(&self) -> String {
+        self.interval_histogram
+            .iter()
+            .map(|count| count.to_string())
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+}
+
+/// Collects the frame timestamps of a noise window stack.
+#[derive(Debug, Default)]
+pub(crate) struct FrameTiming {
+    last: Option<FrameTimestamp>,
+    intervals: Vec<ClockTime>,
+    nominal_interval: Option<ClockTime>,
+    dropped: u64,
+    duplicated: u64,
+}
+
+impl FrameTiming {
+    pub(crate) fn add(&mut self, frame: FrameTimestamp) {
+        self.nominal_interval = frame.nominal_interval;
+        let Some(last) = self.last.replace(frame) else {
+            return;
+        };
+
+        // offsets and timestamps start over after a seek or a loop of a file, which is neither a drop nor a duplicate
+        match (frame.offset, last.offset) {
+            (Some(offset), Some(last_offset)) if offset < last_offset => return,
+            (Some(offset), Some(last_offset)) if offset == last_offset => {
+                self.duplicated += 1;
+                return;
+            }
+            (Some(offset), Some(last_offset)) => self.dropped += offset - last_offset - 1,
+            _ => {}
+        }
+
+        let (Some(pts), Some(last_pts)) = (frame.pts, last.pts) else {
+            return;
+        };
+        if pts < last_pts {
+            return;
+        }
+        let interval = pts - last_pts;
+        if interval == ClockTime::ZERO && frame.offset.is_none() {
+            self.duplicated += 1;
+            return;
+        }
+        if let (None, Some(nominal)) = (frame.offset, frame.nominal_interval.filter(|n| n.nseconds() > 0)) {
+            let ratio = interval.nseconds() as f64 / nominal.nseconds() as f64;
+            if ratio > DROP_FACTOR {
+                self.dropped += ratio.round() as u64 - 1;
+            }
+        }
+        self.intervals.push(interval);
+    }
+
+    /// Returns the statistics of the frames added since the last call.
+    ///
+    /// The last timestamp is kept, so that the interval to the first frame of the next stack is counted too.
+    pub(crate) fn take_stats(&mut self) -> FrameTimingStats {
+        let intervals_ms: Vec<f64> = self.intervals.drain(..).map(|i| i.nseconds() as f64 / 1e6).collect();
+        let dropped_frames = std::mem::take(&mut self.dropped);
+        let duplicated_frames = std::mem::take(&mut self.duplicated);
+        if intervals_ms.is_empty() {
+            return FrameTimingStats {
+                dropped_frames,
+                duplicated_frames,
+                ..FrameTimingStats::default()
+            };
+        }
+
+        let n = intervals_ms.len() as f64;
+        let mean = intervals_ms.iter().sum::<f64>() / n;
+        let variance = intervals_ms.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / n;
+        let reference_ms = self
+            .nominal_interval
+            .filter(|n| n.nseconds() > 0)
+            .map_or(mean, |n| n.nseconds() as f64 / 1e6);
+
+        let mut interval_histogram = [0; HISTOGRAM_BINS];
+        for interval in &intervals_ms {
+            let bin = if reference_ms > 0.0 {
+                (interval / reference_ms / HISTOGRAM_BIN_WIDTH) as usize
+            } else {
+                0
+            };
+            interval_histogram[bin.min(HISTOGRAM_BINS - 1)] += 1;
+        }
+
+        FrameTimingStats {
+            effective_fps: if mean > 0.0 { 1000.0 / mean } else { 0.0 },
+            interval_mean_ms: mean,
+            interval_jitter_ms: variance.sqrt(),
+            interval_min_ms: intervals_ms.iter().copied().fold(f64::INFINITY, f64::min),
+            interval_max_ms: intervals_ms.iter().copied().fold(0.0, f64::max),
+            interval_histogram,
+            dropped_frames,
+            duplicated_frames,
+            skipped_frames: 0,
+            block_timeouts: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL_MS: u64 = 40;
+
+    // frame of a 25 fps source at the given time, numbered if the source counts its frames
+    fn frame(pts_ms: u64, offset: Option<u64>) -> FrameTimestamp {
+        FrameTimestamp {
+            pts: Some(ClockTime::from_mseconds(pts_ms)),
+            offset,
+            nominal_interval: Some(ClockTime::from_mseconds(INTERVAL_MS)),
+            capture_time: None,
+        }
+    }
+
+    #[test]
+    fn consecutive_frames_are_not_dropped() {
+        let mut timing = FrameTiming::default();
+        for idx in 0..10 {
+            timing.add(frame(idx * INTERVAL_MS, Some(idx)));
+        }
+        let stats = timing.take_stats();
+        assert_eq!(stats.dropped_frames, 0);
+        assert_eq!(stats.duplicated_frames, 0);
+        assert!((stats.effective_fps - 25.0).abs() < 1e-9, "{stats:?}");
+        assert!((stats.interval_mean_ms - INTERVAL_MS as f64).abs() < 1e-9);
+        assert!(stats.interval_jitter_ms.abs() < 1e-9);
+        // nine intervals of exactly one nominal interval
+        assert_eq!(stats.interval_histogram, [0, 0, 0, 0, 9, 0, 0, 0]);
+    }
+
+    #[test]
+    fn offset_gap_counts_dropped_frames() {
+        let mut timing = FrameTiming::default();
+        timing.add(frame(0, Some(0)));
+        timing.add(frame(40, Some(1)));
+        // frames 2 and 3 are missing, the interval is not counted a second time
+        timing.add(frame(160, Some(4)));
+        let stats = timing.take_stats();
+        assert_eq!(stats.dropped_frames, 2);
+        assert_eq!(stats.interval_max_ms, 120.0);
+    }
+
+    #[test]
+    fn repeated_offset_is_duplicated_frame() {
+        let mut timing = FrameTiming::default();
+        timing.add(frame(0, Some(0)));
+        timing.add(frame(40, Some(1)));
+        timing.add(frame(40, Some(1)));
+        let stats = timing.take_stats();
+        assert_eq!(stats.duplicated_frames, 1);
+        assert_eq!(stats.dropped_frames, 0);
+    }
+
+    #[test]
+    fn pts_gap_counts_dropped_frames_without_offsets() {
+        let mut timing = FrameTiming::default();
+        timing.add(frame(0, None));
+        // 1.25 intervals is jitter, not a drop
+        timing.add(frame(50, None));
+        // two intervals, more than DROP_FACTOR
+        timing.add(frame(130, None));
+        // four intervals
+        timing.add(frame(290, None));
+        let stats = timing.take_stats();
+        assert_eq!(stats.dropped_frames, 1 + 3);
+        assert_eq!(stats.interval_histogram[HISTOGRAM_BINS - 1], 2);
+    }
+
+    #[test]
+    fn restart_is_neither_dropped_nor_duplicated() {
+        let mut timing = FrameTiming::default();
+        timing.add(frame(1000, Some(25)));
+        timing.add(frame(0, Some(0)));
+        timing.add(frame(40, Some(1)));
+        let stats = timing.take_stats();
+        assert_eq!(stats.dropped_frames, 0);
+        assert_eq!(stats.duplicated_frames, 0);
+    }
+
+    #[test]
+    fn take_stats_resets_the_stats() {
+        let mut timing = FrameTiming::default();
+        timing.add(frame(0, Some(0)));
+        timing.add(frame(120, Some(3)));
+        timing.add(frame(120, Some(3)));
+        let stats = timing.take_stats();
+        assert_eq!((stats.dropped_frames, stats.duplicated_frames), (2, 1));
+
+        // the last frame is kept, so the next stack starts with the interval to it
+        timing.add(frame(160, Some(4)));
+        let stats = timing.take_stats();
+        assert_eq!((stats.dropped_frames, stats.duplicated_frames), (0, 0));
+        assert_eq!(stats.interval_histogram.iter().sum::<u64>(), 1);
+        assert_eq!(stats.interval_mean_ms, INTERVAL_MS as f64);
+
+        assert_eq!(timing.take_stats(), FrameTimingStats::default());
+    }
+}
@@ -0,0 +1,636 @@
+//! Golden reference of a noise measurement, e.g. taken before a firmware update or a change of the ISP tuning.
+//!
+//! A reference stores the measurement report together with the temporal mean and std maps of its noise window. Later
+//! measurements of the main camera are compared with it metric by metric. A metric that grew by more than the
+//! tolerance is flagged as regression, the difference of the std maps shows where the noise changed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::Local;
+use ndarray::{Array2, ShapeError};
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+use slint::{Image, ModelRc, Rgb8Pixel, SharedPixelBuffer, VecModel};
+use thiserror::Error;
+
+use crate::image_pipeline::{
+    config::Settings,
+    export::{MeasurementReport, Metrics},
+    history::NoiseHistory,
+    limits::Limits,
+    noise::NoiseMaps,
+};
+use crate::{App, RegressionRow};
+
+#[derive(Debug, Error)]
+pub enum ReferenceError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid map: {0}")]
+    Shape(#[from] ShapeError),
+}
+
+/// A map of the noise window in row-major order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MapData {
+    rows: usize,
+    cols: usize,
+    values: Vec<f32>,
+}
+
+impl From<&Array2<f32>> for MapData {
+    fn from(map: &Array2<f32>) -> Self {
+        let (rows, cols) = map.dim();
+        MapData {
+            rows,
+            cols,
+            values: map.iter().copied().collect(),
+        }
+    }
+}
+
+impl TryFrom<&MapData> for Array2<f32> {
+    type Error = ShapeError;
+
+    fn try_from(map: &MapData) -> Result<Self, Self::Error> {
+        Array2::from_shape_vec((map.rows, map.cols), map.values.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct GoldenReference {
+    pub report: MeasurementReport,
+    temporal_mean: MapData,
+    temporal_std: MapData,
+}
+
+/// A reference with its maps, ready to be compared.
+struct LoadedReference {
+    path: PathBuf,
+    report: MeasurementReport,
+    temporal_mean: Array2<f32>,
+    temporal_std: Array2<f32>,
+}
+
+impl GoldenReference {
+    pub(crate) fn new(report: MeasurementReport, maps: &NoiseMaps) -> Self {
+        GoldenReference {
+            report,
+            temporal_mean: MapData::from(&maps.temporal_mean),
+            temporal_std: MapData::from(&maps.temporal_std),
+        }
+    }
+
+    pub(crate) fn load(path: &Path) -> Result<Self, ReferenceError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> Result<(), ReferenceError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+impl LoadedReference {
+    fn open(path: &Path) -> Result<Self, ReferenceError> {
+        let reference = GoldenReference::load(path)?;
+        Ok(LoadedReference {
+            path: path.to_path_buf(),
+            temporal_mean: Array2::try_from(&reference.temporal_mean)?,
+            temporal_std: Array2::try_from(&reference.temporal_std)?,
+            report: reference.report,
+        })
+    }
+
+    fn info(&self) -> String {
+        let report = &self.report;
+        format!(
+            "Reference {}: {} {}x{} @ {}, ROI {}x{} at {},{}, {} frames, measured {}",
+            self.path.display(),
+            report.device_path,
+            report.width,
+            report.height,
+            report.framerate,
+            report.roi.w,
+            report.roi.h,
+            report.roi.x,
+            report.roi.y,
+            report.frame_count,
+            report.timestamp,
+        )
+    }
+}
+
+/// The change of a metric that is flagged as regression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Regression {
+    Increase,
+    Decrease,
+    Unchecked,
+}
+
+/// Compares the metrics with the reference, a noise metric that grew or an SNR that dropped by more than the tolerance
+/// in percent is flagged as regression. The mean level of the noise window is shown, but never flagged.
+fn regression_rows(
+    reference: &LoadedReference,
+    metrics: &Metrics,
+    maps: &NoiseMaps,
+    tolerance: f32,
+) -> Vec<RegressionRow> {
+    let row = |name: &str, reference: f32, current: f32, regression: Regression| {
+        let delta = if reference != 0.0 {
+            format!(
+                "{:+.3} ({:+.1} %)",
+                current - reference,
+                (current - reference) / reference * 100.0
+            )
+        } else {
+            format!("{:+.3}", current - reference)
+        };
+        RegressionRow {
+            name: name.into(),
+            reference: format!("{reference:.3}").into(),
+            current: format!("{current:.3}").into(),
+            delta: delta.into(),
+            regressed: match regression {
+                Regression::Increase => current > reference * (1.0 + tolerance / 100.0),
+                Regression::Decrease => current < reference * (1.0 - tolerance / 100.0),
+                Regression::Unchecked => false,
+            },
+        }
+    };
+    let ref_metrics = &reference.report.metrics;
+    let mut rows = vec![
+        row(
+            "Temporal noise",
+            ref_metrics.temporal_noise,
+            metrics.temporal_noise,
+            Regression::Increase,
+        ),
+        row(
+            "Fixed pattern noise",
+            ref_metrics.fixed_pattern_noise,
+            metrics.fixed_pattern_noise,
+            Regression::Increase,
+        ),
+        row(
+            "Row noise",
+            ref_metrics.row_noise,
+            metrics.row_noise,
+            Regression::Increase,
+        ),
+        row(
+            "Column noise",
+            ref_metrics.column_noise,
+            metrics.column_noise,
+            Regression::Increase,
+        ),
+    ];
+    // references stored before the SNR or the defect pixels were calculated have none
+    if let (Some(ref_snr), Some(snr)) = (ref_metrics.snr, metrics.snr) {
+        rows.push(row("SNR", ref_snr, snr, Regression::Decrease));
+    }
+    if let (Some(ref_defect_pixels), Some(defect_pixels)) = (ref_metrics.defect_pixels, metrics.defect_pixels) {
+        rows.push(row(
+            "Defect pixels",
+            ref_defect_pixels as f32,
+            defect_pixels as f32,
+            Regression::Increase,
+        ));
+    }
+    rows.push(row(
+        "Mean level",
+        reference.temporal_mean.mean().unwrap_or_default(),
+        maps.temporal_mean.mean().unwrap_or_default(),
+        Regression::Unchecked,
+    ));
+    rows
+}
+
+// describes how the setup of the measurement differs from the reference, the metrics of a different resolution, noise
+// window or frame count are not comparable one to one
+fn setup_difference(reference: &MeasurementReport, current: &MeasurementReport) -> Option<String> {
+    let setup = |report: &MeasurementReport| {
+        format!(
+            "{}x{}, ROI {}x{} at {},{}, {} frames",
+            report.width, report.height, report.roi.w, report.roi.h, report.roi.x, report.roi.y, report.frame_count
+        )
+    };
+    let (reference, current) = (setup(reference), setup(current));
+    (reference != current).then(|| format!("The measurement ({current}) differs from the reference ({reference})."))
+}
+
+/// Renders the difference of the std maps, blue where the noise decreased and red where it increased, and returns it
+/// together with the difference mapped to full saturation. None if the maps differ in size.
+fn std_difference(reference: &Array2<f32>, current: &Array2<f32>) -> Option<(SharedPixelBuffer<Rgb8Pixel>, f32)> {
+    if reference.dim() != current.dim() {
+        return None;
+    }
+    let diff = current - reference;
+    let max = diff.fold(0.0_f32, |acc, v| acc.max(v.abs()));
+    let scale = if max > 0.0 { 1.0 / max } else { 0.0 };
+    let (rows, cols) = diff.dim();
+
+    let mut buffer = SharedPixelBuffer::<Rgb8Pixel>::new(cols as u32, rows as u32);
+    for (pixel, value) in buffer.make_mut_slice().iter_mut().zip(diff.iter()) {
+        let t = (value * scale).clamp(-1.0, 1.0);
+        // white for no change
+        let fade = ((1.0 - t.abs()) * 255.0) as u8;
+        *pixel = if t < 0.0 {
+            Rgb8Pixel::new(fade, fade, 255)
+        } else {
+            Rgb8Pixel::new(255, fade, fade)
+        };
+    }
+    Some((buffer, max))
+}
+
+/// Parses the tolerance entry in percent.
+fn parse_tolerance(tolerance: &str) -> Option<f32> {
+    tolerance
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|tolerance| tolerance.is_finite() && *tolerance >= 0.0)
+}
+
+impl App {
+    /// Sets up the reference panel, the reference of the settings is loaded right away.
+    pub(crate) fn init_reference_panel(self: &Arc<App>, history: Arc<Mutex<NoiseHistory>>, settings: &Settings) {
+        if let Some(tolerance) = settings.regression_tolerance {
+            self.set_reference_tolerance(tolerance.to_string().into());
+        }
+        let mut reference = None;
+        if let Some(path) = &settings.golden_reference {
+            match LoadedReference::open(Path::new(path)) {
+                Ok(loaded) => reference = Some(loaded),
+                Err(e) => eprintln!("Could not load golden reference {path}: {e}"),
+            }
+        }
+        self.show_reference(reference.as_ref());
+        let reference = Arc::new(Mutex::new(reference));
+
+        self.on_open_reference_panel({
+            let ui = self.clone();
+            let reference = reference.clone();
+            let history = history.clone();
+            move || {
+                ui.compare_with_reference(&reference, &history);
+                ui.set_show_reference_panel(true);
+            }
+        });
+
+        self.on_close_reference_panel({
+            let ui = self.clone();
+            move || ui.set_show_reference_panel(false)
+        });
+
+        self.on_save_reference({
+            let ui = self.clone();
+            let reference = reference.clone();
+            let history = history.clone();
+            move || {
+                let (result, maps) = {
+                    let history = history.lock().expect("History Mutex poisened");
+                    match (history.last(), history.last_maps()) {
+                        (Some(result), Some(maps)) => (result.clone(), maps.clone()),
+                        _ => {
+                            ui.set_reference_status("No measurement to save as reference.".into());
+                            return;
+                        }
+                    }
+                };
+                let file_name = Local::now()
+                    .format("%Y-%m-%d_%H:%M:%S_golden_reference.json")
+                    .to_string();
+                let Some(path) = FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_directory(ui.get_output_dir().as_str())
+                    .set_file_name(file_name)
+                    .save_file()
+                else {
+                    return;
+                };
+
+                let report = MeasurementReport::new(&result, &ui.limits());
+                match GoldenReference::new(report.clone(), &maps).save(&path) {
+                    Ok(()) => {
+                        let saved = LoadedReference {
+                            path: path.clone(),
+                            report,
+                            temporal_mean: maps.temporal_mean,
+                            temporal_std: maps.temporal_std,
+                        };
+                        println!("Saved golden reference to {}.", path.display());
+                        ui.show_reference(Some(&saved));
+                        *reference.lock().expect("Reference Mutex poisened") = Some(saved);
+                        ui.compare_with_reference(&reference, &history);
+                    }
+                    Err(e) => ui.set_reference_status(format!("Could not save reference: {e}").into()),
+                }
+            }
+        });
+
+        self.on_load_reference({
+            let ui = self.clone();
+            let reference = reference.clone();
+            let history = history.clone();
+            move || {
+                let Some(path) = FileDialog::new().add_filter("JSON", &["json"]).pick_file() else {
+                    return;
+                };
+                match LoadedReference::open(&path) {
+                    Ok(loaded) => {
+                        ui.show_reference(Some(&loaded));
+                        *reference.lock().expect("Reference Mutex poisened") = Some(loaded);
+                        ui.compare_with_reference(&reference, &history);
+                    }
+                    Err(e) => ui.set_reference_status(format!("Could not load {}: {e}", path.display()).into()),
+                }
+            }
+        });
+
+        self.on_compare_reference({
+            let ui = self.clone();
+            move || ui.compare_with_reference(&reference, &history)
+        });
+    }
+
+    /// Returns the path of the loaded reference and the tolerance, e.g. to store them in the settings.
+    pub(crate) fn reference_settings(&self) -> (Option<String>, Option<f32>) {
+        let path = self.get_reference_path();
+        (
+            (!path.is_empty()).then(|| path.to_string()),
+            parse_tolerance(&self.get_reference_tolerance()),
+        )
+    }
+
+    fn show_reference(&self, reference: Option<&LoadedReference>) {
+        match reference {
+            Some(reference) => {
+                self.set_reference_info(reference.info().into());
+                self.set_reference_path(reference.path.to_string_lossy().to_string().into());
+            }
+            None => {
+                self.set_reference_info("No golden reference loaded.".into());
+                self.set_reference_path(Default::default());
+            }
+        }
+    }
+
+    // compares the last measurement of the main camera with the reference, called after every measurement of the main
+    // camera while a reference is loaded
+    fn compare_with_reference(&self, reference: &Mutex<Option<LoadedReference>>, history: &Mutex<NoiseHistory>) {
+        let reference = reference.lock().expect("Reference Mutex poisened");
+        let Some(reference) = reference.as_ref() else {
+            self.set_reference_status("Save or load a golden reference first.".into());
+            self.set_reference_regressions(Default::default());
+            return;
+        };
+        let Some(tolerance) = parse_tolerance(&self.get_reference_tolerance()) else {
+            self.set_reference_status("The tolerance must be a percentage of at least 0.".into());
+            return;
+        };
+        let history = history.lock().expect("History Mutex poisened");
+        let (Some(result), Some(maps)) = (history.last(), history.last_maps()) else {
+            self.set_reference_status("No measurement to compare yet.".into());
+            return;
+        };
+
+        let rows = regression_rows(reference, &Metrics::from(result), maps, tolerance);
+        let regressions: Vec<String> = rows
+            .iter()
+            .filter(|row| row.regressed)
+            .map(|row| format!("{} {}", row.name, row.delta))
+            .collect();
+        // the banner below the video shows regressions while the panel is closed
+        let banner = if regressions.is_empty() {
+            String::new()
+        } else {
+            format!("Regression against golden reference: {}", regressions.join(", "))
+        };
+        self.set_reference_regressions(banner.into());
+        self.set_reference_comparison(ModelRc::new(VecModel::from(rows)));
+
+        let mut status = match regressions.len() {
+            0 => format!("No regression above {tolerance} %."),
+            n => format!("{n} metrics regressed by more than {tolerance} %."),
+        };
+        match std_difference(&reference.temporal_std, &maps.temporal_std) {
+            Some((buffer, max)) => {
+                self.set_std_difference(Image::from_rgb8(buffer));
+                self.set_difference_scale(format!("blue -{max:.3}, white 0, red +{max:.3}").into());
+            }
+            None => {
+                self.set_std_difference(Image::default());
+                self.set_difference_scale(Default::default());
+                status.push_str(" The noise windows differ in size, no difference image.");
+            }
+        }
+        if let Some(difference) =
+            setup_difference(&reference.report, &MeasurementReport::new(result, &Limits::default()))
+        {
+            status.push(' ');
+            status.push_str(&difference);
+        }
+        self.set_reference_status(status.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{Array1, Array2};
+
+    use super::*;
+    use crate::image_pipeline::export::Roi;
+
+    const TOLERANCE: f32 = 5.0;
+
+    fn metrics() -> Metrics {
+        Metrics {
+            temporal_noise: 1.0,
+            fixed_pattern_noise: 2.0,
+            row_noise: 0.5,
+            column_noise: 0.0,
+            snr: Some(40.0),
+            defect_pixels: Some(0),
+        }
+    }
+
+    fn report(width: i32, height: i32, roi: Roi, metrics: Metrics) -> MeasurementReport {
+        MeasurementReport {
+            timestamp: "2026-10-18T12:00:00+02:00".to_string(),
+            device_path: "/dev/video0".to_string(),
+            width,
+            height,
+            framerate: "30/1".to_string(),
+            pixel_format: Some("YUY2".to_string()),
+            roi,
+            frame_count: 100,
+            metrics,
+            controls: Default::default(),
+            timing: Default::default(),
+            verdict: None,
+            units: Default::default(),
+        }
+    }
+
+    fn maps(rows: usize, cols: usize, level: f32, std: f32) -> NoiseMaps {
+        NoiseMaps {
+            temporal_mean: Array2::from_elem((rows, cols), level),
+            temporal_std: Array2::from_elem((rows, cols), std),
+            row_profile: Array1::from_elem(rows, level),
+            col_profile: Array1::from_elem(cols, level),
+        }
+    }
+
+    fn reference(metrics: Metrics) -> LoadedReference {
+        let maps = maps(4, 4, 100.0, 1.0);
+        LoadedReference {
+            path: PathBuf::from("reference.json"),
+            report: report(640, 480, Roi { x: 0, y: 0, w: 4, h: 4 }, metrics),
+            temporal_mean: maps.temporal_mean,
+            temporal_std: maps.temporal_std,
+        }
+    }
+
+    fn regressed(rows: &[RegressionRow]) -> Vec<&str> {
+        rows.iter()
+            .filter(|row| row.regressed)
+            .map(|row| row.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn equal_metrics_do_not_regress() {
+        let rows = regression_rows(&reference(metrics()), &metrics(), &maps(4, 4, 100.0, 1.0), TOLERANCE);
+        let names: Vec<&str> = rows.iter().map(|row| row.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Temporal noise",
+                "Fixed pattern noise",
+                "Row noise",
+                "Column noise",
+                "SNR",
+                "Defect pixels",
+                "Mean level"
+            ]
+        );
+        assert!(regressed(&rows).is_empty());
+        assert_eq!(rows[0].delta.as_str(), "+0.000 (+0.0 %)");
+    }
+
+    #[test]
+    fn metrics_beyond_the_tolerance_regress() {
+        let current = Metrics {
+            // within the tolerance
+            temporal_noise: 1.04,
+            fixed_pattern_noise: 2.2,
+            // an improvement is never a regression
+            row_noise: 0.25,
+            snr: Some(37.0),
+            defect_pixels: Some(2),
+            ..metrics()
+        };
+        // a changed mean level is shown, but not flagged
+        let rows = regression_rows(&reference(metrics()), &current, &maps(4, 4, 150.0, 1.0), TOLERANCE);
+        assert_eq!(regressed(&rows), ["Fixed pattern noise", "SNR", "Defect pixels"]);
+        assert_eq!(rows[1].delta.as_str(), "+0.200 (+10.0 %)");
+
+        let higher_snr = Metrics {
+            snr: Some(50.0),
+            ..metrics()
+        };
+        assert!(regressed(&regression_rows(
+            &reference(metrics()),
+            &higher_snr,
+            &maps(4, 4, 100.0, 1.0),
+            TOLERANCE
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn zero_reference_regresses_on_any_increase() {
+        // the column noise and the defect pixels of the reference are 0
+        let rows = regression_rows(&reference(metrics()), &metrics(), &maps(4, 4, 100.0, 1.0), TOLERANCE);
+        assert_eq!(rows[3].delta.as_str(), "+0.000");
+        assert!(!rows[3].regressed);
+
+        let current = Metrics {
+            column_noise: 0.01,
+            defect_pixels: Some(1),
+            ..metrics()
+        };
+        let rows = regression_rows(&reference(metrics()), &current, &maps(4, 4, 100.0, 1.0), TOLERANCE);
+        assert_eq!(regressed(&rows), ["Column noise", "Defect pixels"]);
+        assert_eq!(rows[3].delta.as_str(), "+0.010");
+    }
+
+    #[test]
+    fn metrics_missing_in_the_reference_are_not_compared() {
+        let old = Metrics {
+            snr: None,
+            defect_pixels: None,
+            ..metrics()
+        };
+        let rows = regression_rows(&reference(old), &metrics(), &maps(4, 4, 100.0, 1.0), TOLERANCE);
+        assert!(rows.iter().all(|row| row.name != "SNR" && row.name != "Defect pixels"));
+    }
+
+    #[test]
+    fn different_setup_is_reported() {
+        let reference = reference(metrics());
+        let same = report(640, 480, Roi { x: 0, y: 0, w: 4, h: 4 }, metrics());
+        assert_eq!(setup_difference(&reference.report, &same), None);
+
+        let resolution = report(1280, 720, Roi { x: 0, y: 0, w: 4, h: 4 }, metrics());
+        assert_eq!(
+            setup_difference(&reference.report, &resolution).unwrap(),
+            "The measurement (1280x720, ROI 4x4 at 0,0, 100 frames) differs from the reference (640x480, ROI 4x4 at \
+             0,0, 100 frames)."
+        );
+        let roi = report(640, 480, Roi { x: 8, y: 0, w: 4, h: 4 }, metrics());
+        assert!(setup_difference(&reference.report, &roi).is_some());
+
+        // a noise window of a different size has no difference image
+        let larger = maps(8, 8, 100.0, 1.0);
+        assert!(std_difference(&reference.temporal_std, &larger.temporal_std).is_none());
+    }
+
+    #[test]
+    fn std_difference_is_scaled_to_the_largest_change() {
+        let reference = Array2::from_elem((1, 3), 1.0);
+        let current = Array2::from_shape_vec((1, 3), vec![1.0, 3.0, 0.0]).unwrap();
+        let (buffer, max) = std_difference(&reference, &current).unwrap();
+        assert_eq!(max, 2.0);
+        assert_eq!(
+            buffer.as_slice(),
+            [
+                Rgb8Pixel::new(255, 255, 255),
+                Rgb8Pixel::new(255, 0, 0),
+                Rgb8Pixel::new(127, 127, 255)
+            ]
+        );
+
+        // no change at all is white
+        let (buffer, max) = std_difference(&reference, &reference).unwrap();
+        assert_eq!(max, 0.0);
+        assert!(buffer
+            .as_slice()
+            .iter()
+            .all(|&pixel| pixel == Rgb8Pixel::new(255, 255, 255)));
+    }
+}
@@ -1,13 +1,16 @@
-use std::sync::{atomic::AtomicBool, Arc, Mutex, MutexGuard};
+use std::sync::{atomic::AtomicBool, Arc, Mutex};
 
-use gst::{
-    event::{FlushStart, FlushStop, Reconfigure},
-    prelude::*,
-    State,
-};
+use gst::{prelude::*, State};
 use thiserror::Error;
 
-use crate::image_pipeline::{device_caps::RawSourceCaps, frame_handler::FrameHandler};
+use crate::image_pipeline::{
+    config::Settings,
+    device_caps::RawSourceCaps,
+    frame_handler::FrameHandler,
+    image_sequence::SequenceError,
+    source::{StreamInfo, VideoSource},
+    source_switch::SourceSwitcher,
+};
 
 #[derive(Debug, Error)]
 pub enum GstError {
@@ -25,12 +28,26 @@ pub enum GstError {
 
     #[error("Invalid value.")]
     InvalidValue,
+
+    #[error("State change error: {0}")]
+    StateChange(#[from] gst::StateChangeError),
+
+    #[error("Invalid source description: {0}")]
+    InvalidDescription(String),
+
+    #[error("Invalid stream URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Image sequence error: {0}")]
+    Sequence(#[from] SequenceError),
 }
 
 pub struct ImagePipeline {
     pub pipeline: gst::Pipeline,
     pub frame_handler: Option<FrameHandler>,
     pub caps: Arc<Mutex<RawSourceCaps>>,
+    pub source: Arc<Mutex<VideoSource>>,
+    pub switcher: Arc<SourceSwitcher>,
     shutdown_flag: AtomicBool,
 }
 
@@ -42,33 +59,17 @@ impl Drop for ImagePipeline {
 }
 
 impl ImagePipeline {
-    pub fn new() -> Result<Self, GstError> {
+    /// Creates the main pipeline for the capability stored in the settings, the first capability if it is not
+    /// available. The stored noise window is dropped in that case.
+    pub fn new(settings: &mut Settings) -> Result<Self, GstError> {
         gst::init()?;
-        let caps_obj = RawSourceCaps::new()?;
-
-        // create source element
-        let source = gst::ElementFactory::make("v4l2src")
-            .name("source")
-            .build()
-            .expect("Could not create v4l2src element. Make sure the v4l2 plugin is installed.");
-        source.set_property("device", caps_obj.get_current_device_path());
-
-        // set video capabilities to the first available capability
-        let curr_res = caps_obj.get_current_resolution();
-        let curr_framerate = caps_obj.get_current_framerate()?;
-        println!("Selected resolution: {}, framerate: {}", &curr_res, &curr_framerate);
-        let caps = gst::Caps::builder("video/x-raw")
-            .field("width", curr_res.width)
-            .field("height", curr_res.height)
-            .field("framerate", curr_framerate)
-            .build();
-
-        let capsfilter = gst::ElementFactory::make("capsfilter")
-            .name("filter")
-            .build()
-            .expect("Could not create capsfilter element.");
-        capsfilter.set_property("caps", &caps);
+        let mut caps_obj = RawSourceCaps::new()?;
+        settings.select_capability(&mut caps_obj);
+        Self::with_caps(caps_obj)
+    }
 
+    /// Creates a pipeline for the v4l2 capability selected in the given caps, e.g. for an additional camera.
+    pub(crate) fn with_caps(caps_obj: RawSourceCaps) -> Result<Self, GstError> {
         // add convert element to convert to RGB
         let convert = gst::ElementFactory::make("videoconvert")
             .name("convert")
@@ -85,30 +86,41 @@ impl ImagePipeline {
         rgb_filter.set_property("caps", &rgb_caps);
 
         // create appsink element to retrieve frames
-        let sink = gst_app::AppSink::builder().build();
+        // the sink is named, so that it is found in every pipeline of the application
+        let sink = gst_app::AppSink::builder().name("sink").build();
         // configure appsink to emit a signal when a new sample is ready
         sink.set_property("emit-signals", true);
 
-        // build the pipeline
+        // build the pipeline, the selected v4l2 capability is the initial source
         let pipeline = gst::Pipeline::new();
         let sink: gst::Element = sink.upcast();
         pipeline
-            .add_many([&source, &capsfilter, &convert, &rgb_filter, &sink])
+            .add_many([&convert, &rgb_filter, &sink])
             .expect("Failed to add elements to pipeline.");
-        gst::Element::link_many([&source, &capsfilter, &convert, &rgb_filter, &sink])
-            .expect("Failed to link elements in pipeline.");
+        gst::Element::link_many([&convert, &rgb_filter, &sink]).expect("Failed to link elements in pipeline.");
+        let source = VideoSource::V4l2;
+        if let Err(e) = source.add_to_pipeline(&pipeline, &convert, &caps_obj) {
+            eprintln!("Could not create v4l2 source. Make sure the v4l2 plugin is installed.");
+            return Err(e);
+        }
 
+        let caps = Arc::new(Mutex::new(caps_obj));
+        let source = Arc::new(Mutex::new(source));
+        let switcher = Arc::new(SourceSwitcher::new(pipeline.clone(), caps.clone(), source.clone()));
         let img_pipeline = ImagePipeline {
             pipeline,
-            caps: Arc::new(Mutex::new(caps_obj)),
+            caps,
+            source,
+            switcher,
             frame_handler: None,
             shutdown_flag: AtomicBool::new(false),
         };
 
         // set image pipeline to pause state if possible
-        img_pipeline
-            .set_state(State::Paused)
-            .expect("Could not set pipeline into pause state.");
+        if let Err(e) = img_pipeline.set_state(State::Paused) {
+            eprintln!("Could not set pipeline into pause state.");
+            return Err(e.into());
+        }
 
         Ok(img_pipeline)
     }
@@ -156,61 +168,21 @@ impl ImagePipeline {
         };
     }
 
-    pub fn set_video_resolution(
-        pipeline: &gst::Pipeline,
-        cap_idx: usize,
-        caps: Arc<Mutex<RawSourceCaps>>,
-    ) -> Result<(), GstError> {
-        let caps = &mut caps.lock().expect("Caps Mutex poisened");
-        caps.set_resolution(cap_idx)?;
-        update_video_settings(pipeline, caps)
+    /// Returns the format negotiated between the source and the converter.
+    pub fn get_stream_info(pipeline: &gst::Pipeline) -> Option<StreamInfo> {
+        let caps = pipeline.by_name("convert")?.static_pad("sink")?.current_caps()?;
+        let info = gst_video::VideoInfo::from_caps(&caps).ok()?;
+        Some(StreamInfo {
+            width: info.width() as i32,
+            height: info.height() as i32,
+            framerate: info.fps(),
+            format: info.format().to_str().to_string(),
+        })
     }
 
-    pub fn set_framerate(
-        pipeline: &gst::Pipeline,
-        framerate_idx: usize,
-        caps: Arc<Mutex<RawSourceCaps>>,
-    ) -> Result<(), GstError> {
-        let caps = &mut caps.lock().expect("Caps Mutex poisened");
-        caps.set_framerate(framerate_idx)?;
-        update_video_settings(pipeline, caps)
+    pub(crate) fn get_bus(&self) -> gst::Bus {
+        self.pipeline.bus().expect("Pipeline has no bus.")
     }
-
-    #[allow(unused)]
-    fn get_bus(&self) -> Option<gst::Bus> {
-        self.pipeline.bus()
-    }
-}
-
-fn update_video_settings(
-    pipeline: &gst::Pipeline,
-    caps_guard: &mut MutexGuard<'_, RawSourceCaps>,
-) -> Result<(), GstError> {
-    pipeline
-        .by_name("source")
-        .ok_or(GstError::ElementNotFound)?
-        .set_property("device", &caps_guard.get_current_device_path());
-
-    let new_res = caps_guard.get_current_resolution();
-    let fr = caps_guard.get_current_framerate()?;
-    println!("Currently selected framerate: {fr:?}");
-    let pipeline_caps = gst::Caps::builder("video/x-raw")
-        .field("width", new_res.width)
-        .field("height", new_res.height)
-        .field("framerate", fr)
-        .build();
-
-    pipeline.send_event(FlushStart::new());
-    pipeline.send_event(FlushStop::new(true));
-
-    pipeline
-        .by_name("filter")
-        .ok_or(GstError::ElementNotFound)?
-        .set_property("caps", &pipeline_caps);
-
-    pipeline.send_event(Reconfigure::new());
-
-    Ok(())
 }
 
 /// gets the current state of the pipeline (playing, paused, etc.)
@@ -1,15 +1,51 @@
-use std::sync::Arc;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
 
-use gst::{Pipeline, State};
+use gst::{ClockTime, MessageView, Pipeline, State};
 use rfd::FileDialog;
 use slint::{ComponentHandle, ModelRc, SharedString, VecModel};
 use thiserror::Error;
 
-use crate::image_pipeline::noise::Noise;
+use crate::image_pipeline::batch_trigger::BatchTrigger;
+use crate::image_pipeline::bus::BusWatcher;
+use crate::image_pipeline::config::Settings;
+use crate::image_pipeline::device_caps::{self, RawSourceCaps};
+use crate::image_pipeline::drift_log::{DriftLogger, TemperatureSource};
+use crate::image_pipeline::export::{ExportFormat, MeasurementReport};
+use crate::image_pipeline::frame_queue::{FrameQueue, QueuePolicy};
+use crate::image_pipeline::history::{HistoryChart, NoiseHistory};
+use crate::image_pipeline::image_sequence::{ImageSequence, RawFormat, RawPixelFormat};
+use crate::image_pipeline::measurement_db::MeasurementDb;
+use crate::image_pipeline::multi_camera::CameraContext;
+use crate::image_pipeline::network::{self, NetworkMonitor, NetworkStream};
+use crate::image_pipeline::noise::{MetricView, Noise, NoiseContext};
+use crate::image_pipeline::playback::{FrameRange, Playback};
+use crate::image_pipeline::recording::Recording;
+use crate::image_pipeline::report::write_html_report;
+use crate::image_pipeline::source::VideoSource;
+use crate::image_pipeline::source_switch::{SourceSwitcher, SwitchError};
+use crate::image_pipeline::status_log::{LogEntry, LogLevel};
+use crate::image_pipeline::stereo::StereoPairing;
+use crate::image_pipeline::synthetic::{self, SyntheticParams};
 use crate::image_pipeline::{frame_handler::FrameHandler, gstreamer::ImagePipeline};
 
 use crate::App;
 
+// entries of the source combo box following the v4l2 capabilities
+const OPEN_FILE_ENTRY: &str = "Open file…";
+const OPEN_SEQUENCE_ENTRY: &str = "Open image sequence…";
+const SYNTHETIC_ENTRY: &str = "Synthetic test source…";
+const CUSTOM_SOURCE_ENTRY: &str = "Custom pipeline…";
+const NETWORK_ENTRY: &str = "Network stream…";
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
 #[derive(Debug, Error)]
 pub enum UiError {
     #[error("UI already initialized.")]
@@ -18,7 +54,11 @@ pub enum UiError {
 
 impl App {
     // set up link of image pipeline output frames to GUI
-    pub fn link_with_image_pipeline(&self, image_pipeline: &mut ImagePipeline) -> Result<(), UiError> {
+    pub fn link_with_image_pipeline(
+        &self,
+        image_pipeline: &mut ImagePipeline,
+        settings: &Settings,
+    ) -> Result<(), UiError> {
         if image_pipeline.frame_handler.is_some() {
             return Err(UiError::AlreadyInitialized);
         }
@@ -27,27 +67,96 @@ impl App {
             .frame_handler
             .replace(FrameHandler::init(&image_pipeline.pipeline, self.as_weak()));
 
-        self.init_gui_callbacks(image_pipeline);
+        self.init_gui_callbacks(image_pipeline, settings);
         self.init_gui_elements(image_pipeline);
+        self.apply_settings(settings);
 
         Ok(())
     }
 
-    fn init_gui_callbacks(&self, image_pipeline: &mut ImagePipeline) {
+    fn init_gui_callbacks(&self, image_pipeline: &mut ImagePipeline, settings: &Settings) {
         let ui_weak = Arc::new(self.as_weak());
 
         // set up link of image pipeline video controls to GUI
         let pipe = Arc::new(image_pipeline.pipeline.clone());
         let fh = Arc::new(image_pipeline.frame_handler.clone().unwrap());
         let ui = Arc::new(ui_weak.upgrade().expect("Could not upgrade UI."));
+        let history = Arc::new(Mutex::new(NoiseHistory::default()));
+        let drift_log = Arc::new(Mutex::new(None));
+        let noise_threads = Arc::new(AtomicUsize::new(0));
+        let trigger = Arc::new(BatchTrigger::default());
+        let stereo = StereoPairing::start(ui.as_weak());
+        let limits = Arc::new(Mutex::new(settings.limits));
+        let bus = BusWatcher::start(image_pipeline.get_bus());
+        let playback = Playback::new(image_pipeline.pipeline.clone(), &bus);
+        let network_monitor = Arc::new(NetworkMonitor::new(image_pipeline.pipeline.clone(), &bus));
+        let db_path = MeasurementDb::path(settings.database.as_deref());
+        let db = match MeasurementDb::open(&db_path) {
+            Ok(db) => {
+                println!("Storing measurements in {db_path:?}.");
+                Some(Arc::new(Mutex::new(db)))
+            }
+            Err(e) => {
+                eprintln!("Could not open measurement database {db_path:?}: {e}");
+                None
+            }
+        };
 
         // set up callbacks for video controls
         ui.init_on_toggle_play_pause(pipe.clone(), fh.clone());
         ui.init_on_take_screenshot(fh.clone());
-        ui.init_on_selected_video_source(pipe.clone(), &image_pipeline);
-        ui.init_on_selected_framerate(pipe.clone(), &image_pipeline);
+        ui.init_on_selected_queue_policy(pipe.clone(), fh.clone());
+        ui.init_on_set_noise_threads(noise_threads.clone());
+        ui.init_roi_presets(settings.roi_preset.as_deref());
+        ui.init_on_toggle_recording(pipe.clone(), image_pipeline);
+        ui.init_on_selected_video_source(image_pipeline, playback.clone());
+        ui.init_on_selected_framerate(image_pipeline);
+        ui.init_image_sequence_dialog(image_pipeline);
+        ui.init_synthetic_dialog(image_pipeline);
+        ui.init_custom_source_dialog(image_pipeline);
+        ui.init_network_dialog(image_pipeline, network_monitor.clone());
+        ui.init_device_monitor(pipe.clone(), image_pipeline, fh.clone());
+        ui.init_status_log(&bus, image_pipeline.pipeline.clone(), image_pipeline.switcher.clone());
         ui.init_on_choose_output_dir();
-        ui.start_noise_calculation(fh.clone());
+        ui.init_on_clear_history(history.clone());
+        ui.init_on_selected_history_span(history.clone());
+        ui.init_on_toggle_drift_log(pipe.clone(), image_pipeline, drift_log.clone());
+        ui.init_units(settings.units);
+        ui.init_on_apply_limits(limits.clone(), history.clone());
+        ui.init_on_export_results(history.clone());
+        ui.init_on_create_report(history.clone());
+        ui.init_reference_panel(history.clone(), settings);
+        ui.init_file_playback(playback.clone());
+        ui.start_status_polling(playback, network_monitor, fh.frame_queue.clone());
+        ui.init_measurement_browser(db.clone());
+        ui.init_camera_panel(CameraContext {
+            caps: image_pipeline.caps.clone(),
+            source: image_pipeline.source.clone(),
+            db: db.clone(),
+            threads: noise_threads.clone(),
+            trigger: trigger.clone(),
+            stereo: stereo.clone(),
+            rois: settings.camera_rois.clone(),
+            limits: limits.clone(),
+        });
+        ui.start_noise_calculation(
+            fh.clone(),
+            NoiseContext {
+                pipeline: image_pipeline.pipeline.clone(),
+                caps: image_pipeline.caps.clone(),
+                source: image_pipeline.source.clone(),
+                switcher: image_pipeline.switcher.clone(),
+                history,
+                drift_log,
+                db,
+                threads: noise_threads,
+                view: MetricView::Main,
+                trigger,
+                stereo,
+                limits,
+                headless: settings.headless,
+            },
+        );
     }
 
     fn init_on_toggle_play_pause(self: &Arc<App>, pipe: Arc<Pipeline>, fh: Arc<FrameHandler>) {
@@ -64,43 +173,524 @@ impl App {
         self.on_take_screenshot(move || fh.take_screenshot());
     }
 
-    fn init_on_selected_video_source(self: &Arc<App>, pipe: Arc<Pipeline>, img: &ImagePipeline) {
+    fn init_on_selected_queue_policy(self: &Arc<App>, pipe: Arc<Pipeline>, fh: Arc<FrameHandler>) {
+        self.on_selected_queue_policy(move |value| match QueuePolicy::try_from(value.as_str()) {
+            Ok(policy) => fh.set_queue_policy(&pipe, policy),
+            Err(e) => eprintln!("{e}"),
+        });
+    }
+
+    fn init_on_set_noise_threads(self: &Arc<App>, threads: Arc<AtomicUsize>) {
+        self.on_set_noise_threads(move |value| threads.store(value.max(0) as usize, Ordering::Relaxed));
+    }
+
+    fn init_on_toggle_recording(self: &Arc<App>, pipe: Arc<Pipeline>, img: &ImagePipeline) {
+        let recording: Arc<Mutex<Option<Recording>>> = Arc::new(Mutex::new(None));
+        self.on_toggle_recording({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
+            move || {
+                let mut guard = recording.lock().expect("Recording Mutex poisened");
+
+                // stop a running recording
+                if let Some(running) = guard.take() {
+                    if let Err(e) = running.stop() {
+                        eprintln!("Finishing recording failed: {e}");
+                    }
+                    ui.set_recording(false);
+                    return;
+                }
+
+                let source = source_arc.lock().unwrap().clone();
+                let output_dir = PathBuf::from(ui.get_output_dir().as_str());
+                match Recording::start(&pipe, &output_dir, &source, &caps_arc.lock().unwrap()) {
+                    Ok(running) => {
+                        guard.replace(running);
+                        ui.set_recording(true);
+                    }
+                    Err(e) => eprintln!("Could not start recording: {e}"),
+                }
+            }
+        });
+    }
+
+    fn init_on_selected_video_source(self: &Arc<App>, img: &ImagePipeline, playback: Arc<Playback>) {
         self.on_selected_video_source({
             let ui = self.clone();
-            let pipeline_arc = pipe.clone();
+            let switcher = img.switcher.clone();
             let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
             move |value| {
                 let selected_idx = ui.get_current_video_source() as usize;
                 println!("Selected video source: {value}, index: {selected_idx}");
 
-                if ImagePipeline::set_video_resolution(pipeline_arc.as_ref(), selected_idx, caps_arc.clone()).is_err() {
-                    eprintln!("Failed to set video properties for selected source.");
-                };
+                let n_caps = caps_arc.lock().unwrap().get_caps().len();
+                match selected_idx.checked_sub(n_caps) {
+                    Some(0) => {
+                        ui.open_video_file(&switcher, &caps_arc, &source_arc, &playback);
+                        return;
+                    }
+                    Some(1) => {
+                        ui.set_sequence_status("".into());
+                        ui.set_show_sequence_dialog(true);
+                        return;
+                    }
+                    Some(2) => {
+                        ui.set_show_synthetic_dialog(true);
+                        return;
+                    }
+                    Some(3) => {
+                        ui.set_custom_source_status("".into());
+                        ui.set_show_custom_source_dialog(true);
+                        return;
+                    }
+                    Some(_) => {
+                        ui.set_network_status("".into());
+                        ui.set_show_network_dialog(true);
+                        return;
+                    }
+                    None => {}
+                }
 
-                // reset framerate to first index when changing the resolution
+                // a new device, resolution or format always rebuilds the v4l2 source at the first framerate
+                if !ui.report_switch(switcher.switch_to_v4l2(selected_idx), &caps_arc, &source_arc) {
+                    return;
+                }
+                ui.set_file_mode(false);
+                ui.set_network_mode(false);
                 ui.set_curr_fps(0);
                 let framerates = caps_arc.lock().unwrap().get_current_framerates_as_strings();
                 let framerates: VecModel<SharedString> = framerates.iter().map(|f| f.into()).collect();
                 ui.set_framerates(ModelRc::new(framerates));
-                if ImagePipeline::set_framerate(pipeline_arc.as_ref(), 0, caps_arc.clone()).is_err() {
-                    eprintln!("Failed to set video properties for selected source.");
+            }
+        });
+    }
+
+    fn open_video_file(
+        &self,
+        switcher: &SourceSwitcher,
+        caps_arc: &Arc<Mutex<RawSourceCaps>>,
+        source_arc: &Arc<Mutex<VideoSource>>,
+        playback: &Playback,
+    ) {
+        let file = FileDialog::new()
+            .add_filter("Video", &["mp4", "mkv", "avi", "mov", "webm", "ts"])
+            .add_filter("All files", &["*"])
+            .pick_file();
+        let Some(path) = file else {
+            self.show_active_source(caps_arc, source_arc);
+            return;
+        };
+
+        playback.reset_range();
+        if self.report_switch(switcher.switch_to(VideoSource::File(path)), caps_arc, source_arc) {
+            self.set_file_mode(true);
+            self.set_network_mode(false);
+            // the framerate of a file is given by the file itself
+            self.set_framerates(ModelRc::new(VecModel::<SharedString>::default()));
+        }
+    }
+
+    /// Logs the result of a source switch, after a failed switch the combo box shows the active source again.
+    ///
+    /// Returns true if the switch succeeded.
+    fn report_switch(
+        &self,
+        result: Result<(), SwitchError>,
+        caps_arc: &Arc<Mutex<RawSourceCaps>>,
+        source_arc: &Arc<Mutex<VideoSource>>,
+    ) -> bool {
+        match result {
+            Ok(()) => {
+                let source = source_arc.lock().unwrap().to_string();
+                self.add_log_entry(LogEntry::new(LogLevel::Info, format!("Switched to {source}.")));
+                true
+            }
+            Err(e) => {
+                self.add_log_entry(LogEntry::new(LogLevel::Error, e.to_string()));
+                self.show_active_source(caps_arc, source_arc);
+                false
+            }
+        }
+    }
+
+    /// Selects the entry of the active source in the source combo box, e.g. after a canceled file dialog.
+    fn show_active_source(&self, caps_arc: &Arc<Mutex<RawSourceCaps>>, source_arc: &Arc<Mutex<VideoSource>>) {
+        let caps = caps_arc.lock().unwrap();
+        let idx = match *source_arc.lock().unwrap() {
+            // no entry is selected after the active device was removed
+            VideoSource::V4l2 if caps.is_active_device_removed() => {
+                self.set_current_video_source(-1);
+                return;
+            }
+            VideoSource::V4l2 => caps.get_current_cap_idx(),
+            VideoSource::File(_) => caps.get_caps().len(),
+            VideoSource::ImageSequence(_) => caps.get_caps().len() + 1,
+            VideoSource::Synthetic(_) => caps.get_caps().len() + 2,
+            VideoSource::Custom(_) => caps.get_caps().len() + 3,
+            VideoSource::Network(_) => caps.get_caps().len() + 4,
+        };
+        self.set_current_video_source(idx as i32);
+    }
+
+    fn init_image_sequence_dialog(self: &Arc<App>, img: &ImagePipeline) {
+        self.on_choose_sequence_dir({
+            let ui = self.clone();
+            move || {
+                if let Some(dir) = FileDialog::new().pick_folder() {
+                    ui.set_sequence_dir(dir.to_string_lossy().to_string().into());
+                }
+            }
+        });
+
+        self.on_close_sequence_dialog({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
+            move || {
+                ui.set_show_sequence_dialog(false);
+                ui.show_active_source(&caps_arc, &source_arc);
+            }
+        });
+
+        self.on_open_image_sequence({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
+            let switcher = img.switcher.clone();
+            move |dir, width, height, format, fps| {
+                let sequence = match parse_image_sequence(&dir, &width, &height, &format, &fps) {
+                    Ok(sequence) => sequence,
+                    Err(e) => {
+                        ui.set_sequence_status(e.into());
+                        return;
+                    }
+                };
+                let framerate = sequence.framerate;
+
+                let result = switcher.switch_to(VideoSource::ImageSequence(sequence));
+                if let Err(e) = &result {
+                    ui.set_sequence_status(format!("Could not open image sequence: {e}").into());
+                }
+                if ui.report_switch(result, &caps_arc, &source_arc) {
+                    ui.set_show_sequence_dialog(false);
+                    ui.set_file_mode(false);
+                    ui.set_network_mode(false);
+                    ui.set_curr_fps(0);
+                    let framerate = format!("{}/{}", framerate.numer(), framerate.denom());
+                    ui.set_framerates(ModelRc::new(VecModel::from(vec![SharedString::from(framerate)])));
+                }
+            }
+        });
+    }
+
+    fn init_file_playback(self: &Arc<App>, playback: Arc<Playback>) {
+        self.on_seek_file({
+            let playback = playback.clone();
+            move |position| {
+                if let Err(e) = playback.seek(position as f64) {
+                    eprintln!("Seeking failed: {e}");
+                }
+            }
+        });
+
+        self.on_set_looping({
+            let playback = playback.clone();
+            move |looping| playback.set_looping(looping)
+        });
+
+        self.on_apply_frame_range({
+            let playback = playback.clone();
+            move |start, end| {
+                let start = start.trim();
+                let end = end.trim();
+                let range = match (start.parse::<u64>(), end.is_empty(), end.parse::<u64>()) {
+                    (Ok(start), true, _) => FrameRange { start, end: None },
+                    (Ok(start), false, Ok(end)) => FrameRange { start, end: Some(end) },
+                    _ => {
+                        eprintln!("Invalid frame range {start} - {end}.");
+                        return;
+                    }
+                };
+                if let Err(e) = playback.set_range(range) {
+                    eprintln!("Could not apply frame range: {e}");
+                }
+            }
+        });
+    }
+
+    /// Polls the playback position of file sources, the transport statistics of network streams and the fill level of
+    /// the frame queue.
+    fn start_status_polling(
+        &self,
+        playback: Arc<Playback>,
+        network_monitor: Arc<NetworkMonitor>,
+        frame_queue: Arc<FrameQueue>,
+    ) {
+        let ui_weak = self.as_weak();
+        thread::spawn(move || loop {
+            thread::sleep(STATUS_POLL_INTERVAL);
+            let position = playback.position();
+            let network_stats = network_monitor.stats();
+            let queue_status = format!(
+                "{}/{} queued, {} dropped",
+                frame_queue.queued(),
+                frame_queue.capacity(),
+                frame_queue.dropped()
+            );
+            let updated = ui_weak.upgrade_in_event_loop(move |ui| {
+                ui.set_queue_status(queue_status.into());
+                if let (true, Some((position, duration))) = (ui.get_file_mode(), position) {
+                    ui.set_file_position(position as f32);
+                    ui.set_file_duration(duration as f32);
+                }
+                if ui.get_network_mode() {
+                    let status = match network_stats {
+                        Some(stats) => format!("{} {stats}", network::CODEC_WARNING),
+                        None => network::CODEC_WARNING.to_string(),
+                    };
+                    ui.set_stream_status(status.into());
+                }
+            });
+            // the event loop has quit
+            if updated.is_err() {
+                break;
+            }
+        });
+    }
+
+    fn init_synthetic_dialog(self: &Arc<App>, img: &ImagePipeline) {
+        self.on_close_synthetic_dialog({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
+            move || {
+                ui.set_show_synthetic_dialog(false);
+                ui.show_active_source(&caps_arc, &source_arc);
+            }
+        });
+
+        self.on_open_synthetic_source({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
+            let switcher = img.switcher.clone();
+            move |settings| {
+                let params = SyntheticParams {
+                    width: settings.width.max(1) as u32,
+                    height: settings.height.max(1) as u32,
+                    level: settings.level,
+                    temporal_sigma: settings.temporal_sigma.max(0.0),
+                    fpn_sigma: settings.fpn_sigma.max(0.0),
+                    row_banding: settings.row_banding.max(0.0),
+                    column_banding: settings.column_banding.max(0.0),
+                    hot_pixels: settings.hot_pixels.max(0) as u32,
+                    flicker: settings.flicker.max(0.0),
+                    ..SyntheticParams::default()
+                };
+                println!("Starting synthetic test source with {params:?}.");
+
+                ui.set_show_synthetic_dialog(false);
+                if ui.report_switch(
+                    switcher.switch_to(VideoSource::Synthetic(params)),
+                    &caps_arc,
+                    &source_arc,
+                ) {
+                    ui.set_file_mode(false);
+                    ui.set_network_mode(false);
+                    ui.set_curr_fps(0);
+                    ui.set_framerates(ModelRc::new(VecModel::from(vec![SharedString::from(format!(
+                        "{}/1",
+                        synthetic::FRAMERATE
+                    ))])));
+                }
+            }
+        });
+    }
+
+    fn init_custom_source_dialog(self: &Arc<App>, img: &ImagePipeline) {
+        self.on_close_custom_source_dialog({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
+            move || {
+                ui.set_show_custom_source_dialog(false);
+                ui.show_active_source(&caps_arc, &source_arc);
+            }
+        });
+
+        self.on_open_custom_source({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
+            let switcher = img.switcher.clone();
+            move |description| {
+                let description = description.trim();
+                if description.is_empty() {
+                    ui.set_custom_source_status("Enter the source elements.".into());
+                    return;
+                }
+
+                let result = switcher.switch_to(VideoSource::Custom(description.to_string()));
+                if let Err(e) = &result {
+                    ui.set_custom_source_status(e.to_string().into());
+                }
+                if ui.report_switch(result, &caps_arc, &source_arc) {
+                    ui.set_show_custom_source_dialog(false);
+                    ui.set_file_mode(false);
+                    ui.set_network_mode(false);
+                    ui.set_framerates(ModelRc::new(VecModel::<SharedString>::default()));
+                }
+            }
+        });
+    }
+
+    fn init_network_dialog(self: &Arc<App>, img: &ImagePipeline, monitor: Arc<NetworkMonitor>) {
+        self.on_close_network_dialog({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
+            move || {
+                ui.set_show_network_dialog(false);
+                ui.show_active_source(&caps_arc, &source_arc);
+            }
+        });
+
+        self.on_open_network_stream({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
+            let switcher = img.switcher.clone();
+            move |url, latency| {
+                let url = url.trim();
+                if url.is_empty() {
+                    ui.set_network_status("Enter the stream URL.".into());
+                    return;
+                }
+                let latency = latency.trim();
+                let latency_ms = if latency.is_empty() {
+                    network::DEFAULT_LATENCY_MS
+                } else if let Ok(latency_ms) = latency.parse() {
+                    latency_ms
+                } else {
+                    ui.set_network_status("The latency must be a positive integer.".into());
+                    return;
                 };
+
+                let source = VideoSource::Network(NetworkStream {
+                    url: url.to_string(),
+                    latency_ms,
+                });
+                monitor.reset();
+                let result = switcher.switch_to(source);
+                if let Err(e) = &result {
+                    ui.set_network_status(e.to_string().into());
+                }
+                if ui.report_switch(result, &caps_arc, &source_arc) {
+                    ui.set_show_network_dialog(false);
+                    ui.set_file_mode(false);
+                    ui.set_network_mode(true);
+                    ui.set_stream_status(network::CODEC_WARNING.into());
+                    ui.set_framerates(ModelRc::new(VecModel::<SharedString>::default()));
+                }
             }
         });
     }
 
-    fn init_on_selected_framerate(self: &Arc<App>, pipe: Arc<Pipeline>, img: &ImagePipeline) {
+    /// Watches the device monitor for plugged in and removed v4l2 devices and updates the source list.
+    ///
+    /// The video is paused if the device of the active source is removed.
+    fn init_device_monitor(self: &Arc<App>, pipe: Arc<Pipeline>, img: &ImagePipeline, fh: Arc<FrameHandler>) {
+        let ui_weak = self.as_weak();
+        let caps_arc = img.caps.clone();
+        let source_arc = img.source.clone();
+        let bus = caps_arc.lock().expect("Caps Mutex poisened").get_monitor_bus();
+
+        thread::spawn(move || {
+            for msg in bus.iter_timed(ClockTime::NONE) {
+                let (level, message, paused) = match msg.view() {
+                    MessageView::DeviceAdded(added) => {
+                        let device = added.device();
+                        if !caps_arc.lock().expect("Caps Mutex poisened").add_device(&device) {
+                            continue;
+                        }
+                        (
+                            LogLevel::Info,
+                            format!("Video device {} connected.", device.display_name()),
+                            false,
+                        )
+                    }
+                    MessageView::DeviceRemoved(removed) => {
+                        let device = removed.device();
+                        let Some(path) = device_caps::get_v4l2_device_path(&device) else {
+                            continue;
+                        };
+                        let is_v4l2 = *source_arc.lock().expect("Source Mutex poisened") == VideoSource::V4l2;
+                        let removed = caps_arc.lock().expect("Caps Mutex poisened").remove_device(&path);
+                        match removed {
+                            None => continue,
+                            Some(true) if is_v4l2 => {
+                                // the pause image is shown by the event loop, the UI can't be upgraded in this thread
+                                if pipe.set_state(State::Paused).is_err() {
+                                    eprintln!("Failed to pause video.");
+                                }
+                                (
+                                    LogLevel::Warning,
+                                    format!(
+                                        "Active video device {} ({path}) was removed, video paused.",
+                                        device.display_name()
+                                    ),
+                                    true,
+                                )
+                            }
+                            Some(_) => (
+                                LogLevel::Info,
+                                format!("Video device {} ({path}) was removed.", device.display_name()),
+                                false,
+                            ),
+                        }
+                    }
+                    _ => continue,
+                };
+
+                let caps_arc = caps_arc.clone();
+                let source_arc = source_arc.clone();
+                let fh = fh.clone();
+                let updated = ui_weak.upgrade_in_event_loop(move |ui| {
+                    ui.set_video_sources(source_entries(&caps_arc.lock().expect("Caps Mutex poisened")));
+                    ui.show_active_source(&caps_arc, &source_arc);
+                    ui.add_log_entry(LogEntry::new(level, message));
+                    if paused {
+                        fh.display_pause_image();
+                        ui.set_playing(false);
+                        ui.set_framerates(ModelRc::new(VecModel::<SharedString>::default()));
+                    }
+                });
+                // the event loop has quit
+                if updated.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    fn init_on_selected_framerate(self: &Arc<App>, img: &ImagePipeline) {
         self.on_selected_framerate({
             let ui = self.clone();
-            let pipeline_arc = pipe.clone();
+            let switcher = img.switcher.clone();
             let caps_arc = img.caps.clone();
+            let source_arc = img.source.clone();
             move |value| {
+                // files and image sequences have a fixed framerate
+                if *source_arc.lock().unwrap() != VideoSource::V4l2 {
+                    return;
+                }
+
                 let selected_idx = ui.get_curr_fps() as usize;
                 println!("Selected framerate: {value}, index: {selected_idx}");
 
-                if ImagePipeline::set_framerate(pipeline_arc.as_ref(), selected_idx, caps_arc.clone()).is_err() {
-                    eprintln!("Failed to set video properties for selected source.");
-                };
+                ui.report_switch(switcher.set_framerate(selected_idx), &caps_arc, &source_arc);
             }
         });
     }
@@ -117,13 +707,140 @@ impl App {
         });
     }
 
+    fn init_on_clear_history(self: &Arc<App>, history: Arc<Mutex<NoiseHistory>>) {
+        self.on_clear_history({
+            let ui = self.clone();
+            move || {
+                let mut history = history.lock().expect("History Mutex poisened");
+                history.clear();
+                ui.show_history_chart(history.chart());
+            }
+        });
+    }
+
+    fn init_on_selected_history_span(self: &Arc<App>, history: Arc<Mutex<NoiseHistory>>) {
+        self.on_selected_history_span({
+            let ui = self.clone();
+            move |value| {
+                // "All" (or anything else that is not a number) shows the complete history
+                let span = value.parse::<usize>().ok();
+                let mut history = history.lock().expect("History Mutex poisened");
+                history.set_span(span);
+                ui.show_history_chart(history.chart());
+            }
+        });
+    }
+
+    fn init_on_toggle_drift_log(
+        self: &Arc<App>,
+        pipe: Arc<Pipeline>,
+        img: &ImagePipeline,
+        drift_log: Arc<Mutex<Option<DriftLogger>>>,
+    ) {
+        self.on_toggle_drift_log({
+            let ui = self.clone();
+            let caps_arc = img.caps.clone();
+            let switcher = img.switcher.clone();
+            move || {
+                let mut guard = drift_log.lock().expect("Drift log Mutex poisened");
+
+                // stop a running drift log
+                if let Some(logger) = guard.take() {
+                    println!("Stopped drift log {:?}.", logger.path());
+                    ui.set_drift_logging(false);
+                    ui.set_drift_log_status("".into());
+                    return;
+                }
+
+                let interval = Duration::from_secs(ui.get_drift_interval().max(1) as u64);
+                let device_path = caps_arc.lock().unwrap().get_current_device_path().to_string();
+                let temperature = TemperatureSource::parse(ui.get_temperature_source().as_str(), &device_path);
+                let output_dir = PathBuf::from(ui.get_output_dir().as_str());
+
+                match DriftLogger::new(&output_dir, interval, temperature) {
+                    Ok(logger) => {
+                        println!("Started drift log {:?}.", logger.path());
+                        ui.set_drift_log_status(format!("Logging to {}", logger.path().display()).into());
+                        guard.replace(logger);
+                        drop(guard);
+                        DriftLogger::start_watchdog(drift_log.clone(), pipe.as_ref().clone(), switcher.clone());
+                        ui.set_drift_logging(true);
+                    }
+                    Err(e) => {
+                        eprintln!("Could not start drift log: {e}");
+                        ui.set_drift_log_status(format!("Drift log failed: {e}").into());
+                    }
+                }
+            }
+        });
+    }
+
+    fn init_on_export_results(self: &Arc<App>, history: Arc<Mutex<NoiseHistory>>) {
+        self.on_export_results({
+            let ui = self.clone();
+            move |format| {
+                let format = match ExportFormat::try_from(format.as_str()) {
+                    Ok(f) => f,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        return;
+                    }
+                };
+                let Some(result) = history.lock().expect("History Mutex poisened").last().cloned() else {
+                    println!("No noise results to export.");
+                    return;
+                };
+
+                let report = MeasurementReport::new(&result, &ui.limits()).in_units(ui.units());
+                let output_dir = PathBuf::from(ui.get_output_dir().as_str());
+                match report.export(&output_dir, format) {
+                    Ok(path) => println!("Results successfully exported to {path:?}."),
+                    Err(e) => eprintln!("Exporting results failed: {e}"),
+                }
+            }
+        });
+    }
+
+    fn init_on_create_report(self: &Arc<App>, history: Arc<Mutex<NoiseHistory>>) {
+        self.on_create_report({
+            let ui = self.clone();
+            move || {
+                let (result, maps, snapshot) = {
+                    let history = history.lock().expect("History Mutex poisened");
+                    match (history.last(), history.last_maps()) {
+                        (Some(result), Some(maps)) => (result.clone(), maps.clone(), history.last_snapshot().cloned()),
+                        _ => {
+                            println!("No noise results for a report.");
+                            return;
+                        }
+                    }
+                };
+
+                let report = MeasurementReport::new(&result, &ui.limits()).in_units(ui.units());
+                let output_dir = PathBuf::from(ui.get_output_dir().as_str());
+                match write_html_report(&output_dir, &report, &maps, snapshot) {
+                    Ok(path) => println!("Report successfully saved to {path:?}."),
+                    Err(e) => eprintln!("Creating report failed: {e}"),
+                }
+            }
+        });
+    }
+
+    pub(crate) fn show_history_chart(&self, chart: HistoryChart) {
+        self.set_history_temporal_path(chart.temporal_path.into());
+        self.set_history_fpn_path(chart.fpn_path.into());
+        self.set_history_row_path(chart.row_path.into());
+        self.set_history_column_path(chart.col_path.into());
+        self.set_history_y_max(chart.y_max);
+        self.set_history_start(chart.start.into());
+        self.set_history_end(chart.end.into());
+        self.set_history_count(chart.count as i32);
+    }
+
     fn init_gui_elements(&self, image_pipeline: &mut ImagePipeline) {
         let cap_lock = image_pipeline.caps.lock().expect("Could not acquire lock");
-        let cap_vec = cap_lock.get_caps();
-        let available_sources: VecModel<SharedString> =
-            cap_vec.iter().map(|s| s.to_string_wo_framerate().into()).collect();
         let ui = Arc::new(self.as_weak().upgrade().expect("Could not upgrade UI."));
-        ui.set_video_sources(ModelRc::new(available_sources));
+        ui.set_video_sources(source_entries(&cap_lock));
 
         let available_framerates: VecModel<SharedString> = cap_lock
             .get_current_framerates_as_strings()
@@ -131,9 +848,75 @@ impl App {
             .map(|s| s.into())
             .collect();
         ui.set_framerates(ModelRc::new(available_framerates));
+        // the pipeline may start with a restored capability instead of the first one
+        ui.set_current_video_source(cap_lock.get_current_cap_idx() as i32);
+        ui.set_curr_fps(cap_lock.get_current_framerate_idx() as i32);
+
+        let pixel_formats: VecModel<SharedString> = RawPixelFormat::ALL.iter().map(|f| f.to_string().into()).collect();
+        ui.set_sequence_pixel_formats(ModelRc::new(pixel_formats));
     }
 
-    fn start_noise_calculation(self: Arc<App>, fh: Arc<FrameHandler>) {
-        Noise::new(self, fh).expect("Could not start noise calculation.");
+    fn start_noise_calculation(self: Arc<App>, fh: Arc<FrameHandler>, ctx: NoiseContext) {
+        Noise::new(self, fh, ctx).expect("Could not start noise calculation.");
     }
 }
+
+/// Returns the entries of the source combo box: the v4l2 capabilities followed by the other source types.
+fn source_entries(caps: &RawSourceCaps) -> ModelRc<SharedString> {
+    let entries: VecModel<SharedString> = caps
+        .get_caps()
+        .iter()
+        .map(|s| s.to_string_wo_framerate().into())
+        .chain([
+            OPEN_FILE_ENTRY.into(),
+            OPEN_SEQUENCE_ENTRY.into(),
+            SYNTHETIC_ENTRY.into(),
+            CUSTOM_SOURCE_ENTRY.into(),
+            NETWORK_ENTRY.into(),
+        ])
+        .collect();
+    ModelRc::new(entries)
+}
+
+/// Parses the entries of the image sequence dialog.
+fn parse_image_sequence(
+    dir: &str,
+    width: &str,
+    height: &str,
+    format: &str,
+    fps: &str,
+) -> Result<ImageSequence, String> {
+    let dir = PathBuf::from(dir.trim());
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory.", dir.display()));
+    }
+    let positive = |value: &str| value.trim().parse::<u32>().ok().filter(|&value| value > 0);
+    let (Some(width), Some(height)) = (positive(width), positive(height)) else {
+        return Err("Width and height must be positive integers.".to_string());
+    };
+    let pixel_format = RawPixelFormat::try_from(format).map_err(|e| e.to_string())?;
+    // the framerate is given either as fraction (e.g. 30000/1001) or as decimal number
+    let framerate = match fps.trim().split_once('/') {
+        // a fraction with a zero denominator cannot be created
+        Some((numer, denom)) => numer
+            .trim()
+            .parse()
+            .ok()
+            .zip(denom.trim().parse::<i32>().ok().filter(|&d| d != 0))
+            .map(|(n, d)| gst::Fraction::new(n, d)),
+        None => fps.trim().parse::<f64>().ok().and_then(gst::Fraction::approximate_f64),
+    };
+    let framerate = framerate
+        .filter(|f| f.numer() > 0 && f.denom() > 0)
+        .ok_or_else(|| format!("Invalid framerate {fps}."))?;
+
+    Ok(ImageSequence {
+        dir,
+        raw_format: RawFormat {
+            width,
+            height,
+            pixel_format,
+        },
+        framerate,
+    })
+}
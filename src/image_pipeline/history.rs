@@ -0,0 +1,147 @@
+//! Keeps a timestamped history of computed noise metrics and renders it as a line chart.
+
+use std::fmt::Write;
+
+use chrono::prelude::*;
+use image::RgbImage;
+
+use crate::image_pipeline::{
+    export::SourceMetadata,
+    frame_timing::FrameTimingStats,
+    noise::{NoiseConfig, NoiseMaps},
+};
+
+// coordinate system of the chart paths, see `Path` viewbox in the GUI
+const CHART_WIDTH: f32 = 1000.0;
+const CHART_HEIGHT: f32 = 100.0;
+
+/// A single set of noise metrics calculated from one full noise window stack.
+#[derive(Debug, Clone)]
+pub(crate) struct NoiseResult {
+    pub timestamp: DateTime<Local>,
+    pub config: NoiseConfig,
+    pub temporal: f32,
+    pub fpn: f32,
+    pub row: f32,
+    pub col: f32,
+    // None if the noise window has no temporal noise
+    pub snr: Option<f32>,
+    // hot and dead pixels of the noise window
+    pub defect_pixels: usize,
+    pub timing: FrameTimingStats,
+    pub source: SourceMetadata,
+}
+
+/// SVG path commands and axis labels of the history chart.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HistoryChart {
+    pub temporal_path: String,
+    pub fpn_path: String,
+    pub row_path: String,
+    pub col_path: String,
+    pub y_max: f32,
+    pub start: String,
+    pub end: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct NoiseHistory {
+    results: Vec<NoiseResult>,
+    // maps and snapshot are only kept for the most recent result to bound memory usage during long runs
+    last_maps: Option<NoiseMaps>,
+    // the frame that completed the stack of the most recent result, None if it could not be copied
+    last_snapshot: Option<RgbImage>,
+    // number of most recent results shown in the chart, None shows all results
+    span: Option<usize>,
+}
+
+impl NoiseHistory {
+    pub(crate) fn push(&mut self, result: NoiseResult, maps: NoiseMaps, snapshot: Option<RgbImage>) {
+        self.results.push(result);
+        self.last_maps = Some(maps);
+        self.last_snapshot = snapshot;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.results.clear();
+        self.last_maps = None;
+        self.last_snapshot = None;
+    }
+
+    /// Returns the most recent result.
+    pub(crate) fn last(&self) -> Option<&NoiseResult> {
+        self.results.last()
+    }
+
+    /// Returns the noise maps of the most recent result.
+    pub(crate) fn last_maps(&self) -> Option<&NoiseMaps> {
+        self.last_maps.as_ref()
+    }
+
+    /// Returns the snapshot of the frame that completed the most recent result.
+    pub(crate) fn last_snapshot(&self) -> Option<&RgbImage> {
+        self.last_snapshot.as_ref()
+    }
+
+    pub(crate) fn set_span(&mut self, span: Option<usize>) {
+        self.span = span.filter(|s| *s > 1);
+    }
+
+    /// Returns the results within the currently selected zoom span.
+    fn visible(&self) -> &[NoiseResult] {
+        match self.span {
+            Some(span) if span < self.results.len() => &self.results[self.results.len() - span..],
+            _ => &self.results,
+        }
+    }
+
+    /// Renders the visible results into SVG path commands, all metrics sharing the same y axis.
+    pub(crate) fn chart(&self) -> HistoryChart {
+        let visible = self.visible();
+        let (Some(first), Some(last)) = (visible.first(), visible.last()) else {
+            return HistoryChart::default();
+        };
+
+        let y_max = visible
+            .iter()
+            .flat_map(|r| [r.temporal, r.fpn, r.row, r.col])
+            .fold(0.0_f32, f32::max);
+        // avoid division by zero for flat zero signals
+        let y_max = if y_max > 0.0 { y_max } else { 1.0 };
+
+        let path = |metric: fn(&NoiseResult) -> f32| -> String {
+            let x_step = if visible.len() > 1 {
+                CHART_WIDTH / (visible.len() - 1) as f32
+            } else {
+                0.0
+            };
+            let mut cmds = String::new();
+            for (i, r) in visible.iter().enumerate() {
+                let cmd = if i == 0 { 'M' } else { 'L' };
+                let y = CHART_HEIGHT - metric(r) / y_max * CHART_HEIGHT;
+                let _ = write!(cmds, "{cmd} {:.1} {:.2} ", i as f32 * x_step, y);
+            }
+            // a single point is drawn as a horizontal line across the chart
+            if visible.len() == 1 {
+                let _ = write!(
+                    cmds,
+                    "L {CHART_WIDTH:.1} {:.2}",
+                    CHART_HEIGHT - metric(first) / y_max * CHART_HEIGHT
+                );
+            }
+            cmds
+        };
+
+        HistoryChart {
+            temporal_path: path(|r| r.temporal),
+            fpn_path: path(|r| r.fpn),
+            row_path: path(|r| r.row),
+            col_path: path(|r| r.col),
+            y_max,
+            start: first.timestamp.format("%H:%M:%S").to_string(),
+            end: last.timestamp.format("%H:%M:%S").to_string(),
+            count: visible.len(),
+        }
+    }
+}
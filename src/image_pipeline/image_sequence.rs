@@ -0,0 +1,289 @@
+//! Image sequence source: plays directories of image files or raw frame dumps in name order through an appsrc.
+
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use gst::prelude::*;
+use image::DynamicImage;
+use thiserror::Error;
+
+use crate::image_pipeline::{
+    gstreamer::GstError,
+    recording::{RecordingError, RecordingInfo},
+    source::{create_app_source, frame_bytes},
+};
+
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "tif", "tiff", "bmp", "jpg", "jpeg"];
+const RAW_EXTENSIONS: [&str; 2] = ["bin", "raw"];
+
+#[derive(Debug, Error)]
+pub enum SequenceError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+
+    #[error("Bool error: {0}")]
+    BoolError(#[from] gst::glib::BoolError),
+
+    #[error("No image or raw frame files found in {0:?}.")]
+    NoFrames(PathBuf),
+
+    #[error("Frame {0:?} does not match the format of the sequence.")]
+    FrameMismatch(PathBuf),
+
+    #[error("Invalid framerate: {0}")]
+    InvalidFramerate(gst::Fraction),
+
+    #[error("Invalid frame size: {0}x{1}")]
+    InvalidFrameSize(u32, u32),
+
+    #[error("Recording error: {0}")]
+    Recording(#[from] RecordingError),
+
+    #[error("Unknown raw pixel format: {0}")]
+    UnknownPixelFormat(String),
+}
+
+/// Pixel formats of raw frame dumps, stored without padding between rows, the planes of planar formats follow each
+/// other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RawPixelFormat {
+    Gray8,
+    Gray16Le,
+    Rgb,
+    Bgr,
+    Yuy2,
+    /// output of the MJPEG and H.264 decoders
+    Nv12,
+    I420,
+    Y42b,
+}
+
+impl RawPixelFormat {
+    pub const ALL: [RawPixelFormat; 8] = [
+        RawPixelFormat::Gray8,
+        RawPixelFormat::Gray16Le,
+        RawPixelFormat::Rgb,
+        RawPixelFormat::Bgr,
+        RawPixelFormat::Yuy2,
+        RawPixelFormat::Nv12,
+        RawPixelFormat::I420,
+        RawPixelFormat::Y42b,
+    ];
+
+    fn video_format(self) -> gst_video::VideoFormat {
+        match self {
+            RawPixelFormat::Gray8 => gst_video::VideoFormat::Gray8,
+            RawPixelFormat::Gray16Le => gst_video::VideoFormat::Gray16Le,
+            RawPixelFormat::Rgb => gst_video::VideoFormat::Rgb,
+            RawPixelFormat::Bgr => gst_video::VideoFormat::Bgr,
+            RawPixelFormat::Yuy2 => gst_video::VideoFormat::Yuy2,
+            RawPixelFormat::Nv12 => gst_video::VideoFormat::Nv12,
+            RawPixelFormat::I420 => gst_video::VideoFormat::I420,
+            RawPixelFormat::Y42b => gst_video::VideoFormat::Y42b,
+        }
+    }
+}
+
+impl Display for RawPixelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.video_format().to_str())
+    }
+}
+
+impl TryFrom<&str> for RawPixelFormat {
+    type Error = SequenceError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        RawPixelFormat::ALL
+            .into_iter()
+            .find(|f| f.to_string().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| SequenceError::UnknownPixelFormat(value.to_string()))
+    }
+}
+
+/// Frame format of raw dumps, image files carry their own format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawFormat {
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: RawPixelFormat,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageSequence {
+    pub dir: PathBuf,
+    pub raw_format: RawFormat,
+    pub framerate: gst::Fraction,
+}
+
+impl ImageSequence {
+    /// Creates the source element that plays the frames of the directory in a loop at the given framerate.
+    pub(crate) fn create_source(&self) -> Result<gst::Element, GstError> {
+        let mut reader = FrameReader::open(self)?;
+        println!(
+            "Playing image sequence {:?} with {} frame files.",
+            self.dir,
+            reader.files.len()
+        );
+
+        create_app_source(reader.info.clone(), move || reader.next_frame())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameKind {
+    Image,
+    Raw,
+}
+
+/// Reads the frames of a sequence one after another, restarting with the first file after the last one.
+struct FrameReader {
+    files: Vec<PathBuf>,
+    kind: FrameKind,
+    format: RawFormat,
+    info: gst_video::VideoInfo,
+    file_idx: usize,
+    // raw dumps may hold several frames, the remaining frames of the current dump are read from here
+    raw_data: Vec<u8>,
+    raw_offset: usize,
+}
+
+impl FrameReader {
+    fn open(sequence: &ImageSequence) -> Result<Self, SequenceError> {
+        // recordings describe their frames in a sidecar, which replaces the entered format
+        let (raw_format, framerate) = match RecordingInfo::read(&sequence.dir)? {
+            Some(recording) => {
+                println!(
+                    "Replaying recording of {} from {}.",
+                    recording.source, recording.started
+                );
+                let format = RawFormat {
+                    width: recording.width,
+                    height: recording.height,
+                    pixel_format: RawPixelFormat::try_from(recording.pixel_format.as_str())?,
+                };
+                // a fraction with a zero denominator cannot be created
+                if recording.framerate_denom == 0 {
+                    return Err(SequenceError::InvalidFramerate(gst::Fraction::new(0, 1)));
+                }
+                (
+                    format,
+                    gst::Fraction::new(recording.framerate_numer, recording.framerate_denom),
+                )
+            }
+            None => (sequence.raw_format, sequence.framerate),
+        };
+        if framerate.numer() <= 0 || framerate.denom() <= 0 {
+            return Err(SequenceError::InvalidFramerate(framerate));
+        }
+
+        // image files take precedence, raw dumps are only played if there are no images
+        let (kind, files) = match list_files(&sequence.dir, &IMAGE_EXTENSIONS)? {
+            images if !images.is_empty() => (FrameKind::Image, images),
+            _ => (FrameKind::Raw, list_files(&sequence.dir, &RAW_EXTENSIONS)?),
+        };
+        if files.is_empty() {
+            return Err(SequenceError::NoFrames(sequence.dir.clone()));
+        }
+
+        // the first image determines the format of an image sequence
+        let format = match kind {
+            FrameKind::Image => {
+                let image = image::open(&files[0])?;
+                RawFormat {
+                    width: image.width(),
+                    height: image.height(),
+                    pixel_format: match image {
+                        DynamicImage::ImageLuma8(_) => RawPixelFormat::Gray8,
+                        DynamicImage::ImageLuma16(_) => RawPixelFormat::Gray16Le,
+                        _ => RawPixelFormat::Rgb,
+                    },
+                }
+            }
+            FrameKind::Raw => raw_format,
+        };
+        if format.width == 0 || format.height == 0 {
+            return Err(SequenceError::InvalidFrameSize(format.width, format.height));
+        }
+        let info = gst_video::VideoInfo::builder(format.pixel_format.video_format(), format.width, format.height)
+            .fps(framerate)
+            .build()?;
+
+        Ok(FrameReader {
+            files,
+            kind,
+            format,
+            info,
+            file_idx: 0,
+            raw_data: Vec::new(),
+            raw_offset: 0,
+        })
+    }
+
+    /// Reads the next frame without row padding.
+    fn next_frame(&mut self) -> Result<Vec<u8>, SequenceError> {
+        match self.kind {
+            FrameKind::Image => {
+                let path = self.next_file();
+                let image = image::open(&path)?;
+                if image.width() != self.format.width || image.height() != self.format.height {
+                    return Err(SequenceError::FrameMismatch(path));
+                }
+                Ok(match self.format.pixel_format {
+                    RawPixelFormat::Gray8 => image.into_luma8().into_raw(),
+                    RawPixelFormat::Gray16Le => image
+                        .into_luma16()
+                        .into_raw()
+                        .into_iter()
+                        .flat_map(u16::to_le_bytes)
+                        .collect(),
+                    _ => image.into_rgb8().into_raw(),
+                })
+            }
+            FrameKind::Raw => {
+                // raw dumps are split into frames of this size
+                let frame_bytes = frame_bytes(&self.info);
+                if self.raw_offset >= self.raw_data.len() {
+                    let path = self.next_file();
+                    let data = fs::read(&path)?;
+                    if data.is_empty() || data.len() % frame_bytes != 0 {
+                        return Err(SequenceError::FrameMismatch(path));
+                    }
+                    self.raw_data = data;
+                    self.raw_offset = 0;
+                }
+                let frame = self.raw_data[self.raw_offset..self.raw_offset + frame_bytes].to_vec();
+                self.raw_offset += frame_bytes;
+                Ok(frame)
+            }
+        }
+    }
+
+    fn next_file(&mut self) -> PathBuf {
+        let path = self.files[self.file_idx].clone();
+        self.file_idx = (self.file_idx + 1) % self.files.len();
+        path
+    }
+}
+
+/// Lists the files of the directory with one of the given extensions, sorted by name.
+fn list_files(dir: &Path, extensions: &[&str]) -> io::Result<Vec<PathBuf>> {
+    let mut files = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| extensions.contains(&ext.to_ascii_lowercase().as_str()))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,430 @@
+//! Pass/fail limits of the noise metrics for production testing.
+//!
+//! Every noise metric and the defect pixel count have an optional upper limit, the SNR an optional lower limit. A measurement passes if every
+//! metric is within its limit, the verdict is shown in the GUI and written into every exported result together with
+//! the limits it was checked against.
+
+use std::{
+    fmt::Write,
+    process::ExitCode,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::image_pipeline::{export::Metrics, history::NoiseHistory};
+use crate::App;
+
+/// Upper limits of the noise metrics and the defect pixel count and lower limit of the SNR, unset limits are not
+/// checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    pub temporal_noise: Option<f32>,
+    pub fixed_pattern_noise: Option<f32>,
+    pub row_noise: Option<f32>,
+    pub column_noise: Option<f32>,
+    pub snr: Option<f32>,
+    pub defect_pixels: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Upper,
+    Lower,
+}
+
+// name, bound, limit and value of a metric
+type LimitEntry = (&'static str, Bound, Option<f32>, fn(&Metrics) -> Option<f32>);
+
+/// Result of checking the metrics of a measurement against the limits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Verdict {
+    pub passed: bool,
+    pub limits: Limits,
+    /// metrics outside their limit
+    pub failed: Vec<String>,
+}
+
+impl Limits {
+    fn is_empty(&self) -> bool {
+        self.entries().iter().all(|(_, _, limit, _)| limit.is_none())
+    }
+
+    // a metric without value, e.g. the SNR of a window without temporal noise, is within its limit
+    fn entries(&self) -> [LimitEntry; 6] {
+        [
+            ("temporal_noise", Bound::Upper, self.temporal_noise, |m| {
+                Some(m.temporal_noise)
+            }),
+            ("fixed_pattern_noise", Bound::Upper, self.fixed_pattern_noise, |m| {
+                Some(m.fixed_pattern_noise)
+            }),
+            ("row_noise", Bound::Upper, self.row_noise, |m| Some(m.row_noise)),
+            ("column_noise", Bound::Upper, self.column_noise, |m| {
+                Some(m.column_noise)
+            }),
+            ("snr", Bound::Lower, self.snr, |m| m.snr),
+            ("defect_pixels", Bound::Upper, self.defect_pixels, |m| {
+                m.defect_pixels.map(|defect_pixels| defect_pixels as f32)
+            }),
+        ]
+    }
+
+    /// Checks the metrics, None if no limit is set.
+    pub(crate) fn check(&self, metrics: &Metrics) -> Option<Verdict> {
+        if self.is_empty() {
+            return None;
+        }
+        let failed: Vec<String> = self
+            .entries()
+            .into_iter()
+            .filter(|(_, bound, limit, value)| match (limit, value(metrics)) {
+                (Some(limit), Some(value)) => match bound {
+                    Bound::Upper => value > *limit,
+                    Bound::Lower => value < *limit,
+                },
+                _ => false,
+            })
+            .map(|(name, _, _, _)| name.to_string())
+            .collect();
+        Some(Verdict {
+            passed: failed.is_empty(),
+            limits: *self,
+            failed,
+        })
+    }
+
+    /// Returns the limits with the noise limits multiplied by the given factor, e.g. to convert them to other units.
+    pub(crate) fn scaled(self, factor: f32) -> Limits {
+        let scale = |limit: Option<f32>| limit.map(|limit| limit * factor);
+        Limits {
+            temporal_noise: scale(self.temporal_noise),
+            fixed_pattern_noise: scale(self.fixed_pattern_noise),
+            row_noise: scale(self.row_noise),
+            column_noise: scale(self.column_noise),
+            ..self
+        }
+    }
+
+    /// Returns the limits as `name<=limit` or `name>=limit` pairs separated by semicolons, e.g. for a CSV field.
+    pub(crate) fn to_compact_string(self) -> String {
+        self.entries()
+            .into_iter()
+            .filter_map(|(name, bound, limit, _)| {
+                let op = match bound {
+                    Bound::Upper => "<=",
+                    Bound::Lower => ">=",
+                };
+                limit.map(|limit| format!("{name}{op}{limit}"))
+            })
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+impl Verdict {
+    pub(crate) fn label(&self) -> &'static str {
+        if self.passed {
+            "PASS"
+        } else {
+            "FAIL"
+        }
+    }
+}
+
+/// Parses the limit entries of the GUI.
+fn parse_limits(
+    temporal: &str,
+    fpn: &str,
+    row: &str,
+    column: &str,
+    snr: &str,
+    defect_pixels: &str,
+) -> Result<Limits, String> {
+    Ok(Limits {
+        temporal_noise: parse_limit("temporal noise", temporal)?,
+        fixed_pattern_noise: parse_limit("fixed pattern noise", fpn)?,
+        row_noise: parse_limit("row noise", row)?,
+        column_noise: parse_limit("column noise", column)?,
+        snr: parse_limit("SNR", snr)?,
+        defect_pixels: parse_limit("defect pixel", defect_pixels)?,
+    })
+}
+
+// an empty entry removes the limit
+fn parse_limit(name: &str, value: &str) -> Result<Option<f32>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse::<f32>() {
+        Ok(limit) if limit.is_finite() && limit >= 0.0 => Ok(Some(limit)),
+        _ => Err(format!("Invalid {name} limit: {value}")),
+    }
+}
+
+// entry of the limit boxes, unset limits are empty, rounded to hide the error of the unit conversion
+fn limit_entry(limit: Option<f32>) -> String {
+    limit
+        .map(|limit| ((limit * 10000.0).round() / 10000.0).to_string())
+        .unwrap_or_default()
+}
+
+impl App {
+    pub(crate) fn init_on_apply_limits(self: &Arc<App>, limits: Arc<Mutex<Limits>>, history: Arc<Mutex<NoiseHistory>>) {
+        self.show_limits(&limits.lock().expect("Limits Mutex poisened"));
+
+        self.on_apply_limits({
+            let ui = self.clone();
+            move |temporal, fpn, row, column, snr, defect_pixels| {
+                // the entries are in the selected units, the limits are applied in DN
+                let new_limits = match parse_limits(&temporal, &fpn, &row, &column, &snr, &defect_pixels) {
+                    Ok(new_limits) => new_limits.scaled(1.0 / ui.units().per_dn()),
+                    Err(e) => {
+                        ui.set_limit_status(e.into());
+                        return;
+                    }
+                };
+
+                *limits.lock().expect("Limits Mutex poisened") = new_limits;
+                ui.show_limits(&new_limits);
+                let status = if new_limits.is_empty() {
+                    "No limits."
+                } else {
+                    "Limits applied."
+                };
+                ui.set_limit_status(status.into());
+                // the last measurement is checked against the new limits right away
+                let verdict = history
+                    .lock()
+                    .expect("History Mutex poisened")
+                    .last()
+                    .and_then(|result| new_limits.check(&Metrics::from(result)));
+                ui.show_verdict(verdict.as_ref());
+            }
+        });
+    }
+
+    /// Returns the applied limits.
+    pub(crate) fn limits(&self) -> Limits {
+        let limit = |value: f32| (value >= 0.0).then_some(value);
+        Limits {
+            temporal_noise: limit(self.get_temporal_noise_limit()),
+            fixed_pattern_noise: limit(self.get_fixed_pattern_noise_limit()),
+            row_noise: limit(self.get_row_noise_limit()),
+            column_noise: limit(self.get_column_noise_limit()),
+            snr: limit(self.get_snr_limit()),
+            defect_pixels: limit(self.get_defect_pixels_limit()),
+        }
+    }
+
+    /// Shows the applied limits, the metric grid colors the metrics against the limits in DN, negative limits are
+    /// not set, and the entries show them in the selected units.
+    pub(crate) fn show_limits(&self, limits: &Limits) {
+        self.set_temporal_noise_limit(limits.temporal_noise.unwrap_or(-1.0));
+        self.set_fixed_pattern_noise_limit(limits.fixed_pattern_noise.unwrap_or(-1.0));
+        self.set_row_noise_limit(limits.row_noise.unwrap_or(-1.0));
+        self.set_column_noise_limit(limits.column_noise.unwrap_or(-1.0));
+        self.set_snr_limit(limits.snr.unwrap_or(-1.0));
+        self.set_defect_pixels_limit(limits.defect_pixels.unwrap_or(-1.0));
+        let limits = limits.scaled(self.units().per_dn());
+        self.set_temporal_noise_limit_entry(limit_entry(limits.temporal_noise).into());
+        self.set_fixed_pattern_noise_limit_entry(limit_entry(limits.fixed_pattern_noise).into());
+        self.set_row_noise_limit_entry(limit_entry(limits.row_noise).into());
+        self.set_column_noise_limit_entry(limit_entry(limits.column_noise).into());
+        self.set_snr_limit_entry(limit_entry(limits.snr).into());
+        self.set_defect_pixels_limit_entry(limit_entry(limits.defect_pixels).into());
+    }
+
+    /// Returns the exit code of a headless run for the verdict of the last measurement: success if it passed, 1 if it
+    /// failed and 2 if no limit is set.
+    pub fn verdict_exit_code(&self) -> ExitCode {
+        let verdict = self.get_verdict();
+        if verdict.is_empty() {
+            eprintln!("No limits set, the measurement has no verdict.");
+            return ExitCode::from(2);
+        }
+        println!("{verdict}");
+        if self.get_verdict_passed() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+
+    /// Shows the PASS/FAIL banner, None hides it.
+    pub(crate) fn show_verdict(&self, verdict: Option<&Verdict>) {
+        let Some(verdict) = verdict else {
+            self.set_verdict(Default::default());
+            return;
+        };
+        let mut text = verdict.label().to_string();
+        if !verdict.passed {
+            let _ = write!(text, ": {} outside limit", verdict.failed.join(", ").replace('_', " "));
+        }
+        self.set_verdict(text.into());
+        self.set_verdict_passed(verdict.passed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> Metrics {
+        Metrics {
+            temporal_noise: 1.0,
+            fixed_pattern_noise: 2.0,
+            row_noise: 0.5,
+            column_noise: 0.25,
+            snr: Some(40.0),
+            defect_pixels: Some(3),
+        }
+    }
+
+    #[test]
+    fn unset_limits_are_not_checked() {
+        assert_eq!(Limits::default().check(&metrics()), None);
+    }
+
+    #[test]
+    fn metrics_within_limits_pass() {
+        let limits = Limits {
+            temporal_noise: Some(1.0),
+            fixed_pattern_noise: Some(2.5),
+            row_noise: Some(0.5),
+            column_noise: Some(1.0),
+            snr: Some(40.0),
+            defect_pixels: Some(3.0),
+        };
+        let verdict = limits.check(&metrics()).unwrap();
+        assert!(verdict.passed);
+        assert!(verdict.failed.is_empty());
+        assert_eq!(verdict.limits, limits);
+        assert_eq!(verdict.label(), "PASS");
+    }
+
+    #[test]
+    fn upper_limits_fail_above_the_limit() {
+        let limits = Limits {
+            temporal_noise: Some(0.9),
+            row_noise: Some(1.0),
+            defect_pixels: Some(2.0),
+            ..Default::default()
+        };
+        let verdict = limits.check(&metrics()).unwrap();
+        assert!(!verdict.passed);
+        assert_eq!(verdict.failed, ["temporal_noise", "defect_pixels"]);
+        assert_eq!(verdict.label(), "FAIL");
+    }
+
+    #[test]
+    fn snr_fails_below_its_lower_limit() {
+        let limits = Limits {
+            snr: Some(50.0),
+            ..Default::default()
+        };
+        assert_eq!(limits.check(&metrics()).unwrap().failed, ["snr"]);
+
+        let limits = Limits {
+            snr: Some(30.0),
+            ..Default::default()
+        };
+        assert!(limits.check(&metrics()).unwrap().passed);
+    }
+
+    #[test]
+    fn metrics_without_value_pass() {
+        let limits = Limits {
+            snr: Some(50.0),
+            defect_pixels: Some(0.0),
+            ..Default::default()
+        };
+        let metrics = Metrics {
+            snr: None,
+            defect_pixels: None,
+            ..metrics()
+        };
+        assert!(limits.check(&metrics).unwrap().passed);
+    }
+
+    #[test]
+    fn empty_limit_is_unset() {
+        assert_eq!(parse_limit("SNR", ""), Ok(None));
+        assert_eq!(parse_limit("SNR", "  "), Ok(None));
+        assert_eq!(parse_limit("SNR", " 40.5 "), Ok(Some(40.5)));
+        assert_eq!(parse_limit("SNR", "0"), Ok(Some(0.0)));
+    }
+
+    #[test]
+    fn invalid_limits_are_rejected() {
+        for value in ["abc", "1,5", "-1", "-0.1", "NaN", "inf"] {
+            assert_eq!(
+                parse_limit("row noise", value),
+                Err(format!("Invalid row noise limit: {value}")),
+                "{value}"
+            );
+        }
+        assert!(parse_limits("1", "2", "x", "", "", "").is_err());
+    }
+
+    #[test]
+    fn compact_string_round_trip() {
+        let limits = Limits {
+            temporal_noise: Some(1.5),
+            fixed_pattern_noise: None,
+            row_noise: Some(0.4),
+            column_noise: None,
+            snr: Some(40.0),
+            defect_pixels: Some(0.0),
+        };
+        let compact = limits.to_compact_string();
+        assert_eq!(compact, "temporal_noise<=1.5;row_noise<=0.4;snr>=40;defect_pixels<=0");
+
+        // the limits are read back the way the CSV field is parsed by a spreadsheet or script
+        let mut parsed = Limits::default();
+        for pair in compact.split(';') {
+            let (name, limit) = pair.split_once("<=").or_else(|| pair.split_once(">=")).unwrap();
+            let limit = parse_limit(name, limit).unwrap();
+            match name {
+                "temporal_noise" => parsed.temporal_noise = limit,
+                "fixed_pattern_noise" => parsed.fixed_pattern_noise = limit,
+                "row_noise" => parsed.row_noise = limit,
+                "column_noise" => parsed.column_noise = limit,
+                "snr" => parsed.snr = limit,
+                "defect_pixels" => parsed.defect_pixels = limit,
+                _ => panic!("unknown limit {name}"),
+            }
+        }
+        assert_eq!(parsed, limits);
+        assert_eq!(Limits::default().to_compact_string(), "");
+    }
+
+    #[test]
+    fn only_noise_limits_are_scaled() {
+        let limits = Limits {
+            temporal_noise: Some(2.0),
+            fixed_pattern_noise: Some(1.0),
+            row_noise: None,
+            column_noise: Some(0.5),
+            snr: Some(40.0),
+            defect_pixels: Some(3.0),
+        };
+        let scaled = limits.scaled(2.0);
+        assert_eq!(scaled.temporal_noise, Some(4.0));
+        assert_eq!(scaled.fixed_pattern_noise, Some(2.0));
+        assert_eq!(scaled.row_noise, None);
+        assert_eq!(scaled.column_noise, Some(1.0));
+        assert_eq!(scaled.snr, limits.snr);
+        assert_eq!(scaled.defect_pixels, limits.defect_pixels);
+    }
+
+    #[test]
+    fn limit_entry_hides_conversion_error() {
+        let percent = 100.0 / 255.0;
+        assert_eq!(limit_entry(Some(2.0 / percent * percent)), "2");
+        assert_eq!(limit_entry(Some(0.25)), "0.25");
+        assert_eq!(limit_entry(None), "");
+    }
+}
@@ -0,0 +1,200 @@
+//! GUI glue of the measurement database browser: filtering, comparing and re-exporting stored measurements.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use chrono::NaiveDate;
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+
+use crate::image_pipeline::{
+    export::{ExportFormat, MeasurementReport},
+    measurement_db::{MeasurementDb, MeasurementFilter, StoredMeasurement},
+};
+use crate::{App, CompareRow};
+
+// device combo box entry that matches all devices
+const ALL_DEVICES: &str = "All";
+
+#[derive(Default)]
+struct BrowserState {
+    records: Vec<StoredMeasurement>,
+    a: Option<MeasurementReport>,
+    b: Option<MeasurementReport>,
+}
+
+impl App {
+    pub(crate) fn init_measurement_browser(self: &Arc<App>, db: Option<Arc<Mutex<MeasurementDb>>>) {
+        let state = Arc::new(Mutex::new(BrowserState::default()));
+
+        self.on_open_measurement_db({
+            let ui = self.clone();
+            let db = db.clone();
+            let state = state.clone();
+            move || {
+                let Some(db) = &db else {
+                    eprintln!("Measurement database not available.");
+                    return;
+                };
+                let db = db.lock().expect("Database Mutex poisened");
+                let mut devices = vec![SharedString::from(ALL_DEVICES)];
+                match db.devices() {
+                    Ok(d) => devices.extend(d.into_iter().map(SharedString::from)),
+                    Err(e) => eprintln!("Could not query devices: {e}"),
+                }
+                ui.set_measurement_devices(ModelRc::new(VecModel::from(devices)));
+                ui.show_measurements(&db, &MeasurementFilter::default(), &state);
+                ui.set_show_measurement_db(true);
+            }
+        });
+
+        self.on_close_measurement_db({
+            let ui = self.clone();
+            move || ui.set_show_measurement_db(false)
+        });
+
+        self.on_search_measurements({
+            let ui = self.clone();
+            let db = db.clone();
+            let state = state.clone();
+            move |device, from, to| {
+                let Some(db) = &db else {
+                    return;
+                };
+                let (from, to) = match (parse_date(&from), parse_date(&to)) {
+                    (Ok(from), Ok(to)) => (from, to),
+                    _ => {
+                        ui.set_measurement_status("Dates must be given as YYYY-MM-DD.".into());
+                        return;
+                    }
+                };
+                let filter = MeasurementFilter {
+                    device_path: (device.as_str() != ALL_DEVICES && !device.is_empty()).then(|| device.to_string()),
+                    from,
+                    to,
+                };
+                ui.show_measurements(&db.lock().expect("Database Mutex poisened"), &filter, &state);
+            }
+        });
+
+        self.on_select_comparison_a({
+            let ui = self.clone();
+            let state = state.clone();
+            move |idx| {
+                let mut state = state.lock().expect("Browser state Mutex poisened");
+                state.a = state.records.get(idx as usize).map(|r| r.report.clone());
+                ui.show_comparison(&state);
+            }
+        });
+
+        self.on_select_comparison_b({
+            let ui = self.clone();
+            let state = state.clone();
+            move |idx| {
+                let mut state = state.lock().expect("Browser state Mutex poisened");
+                state.b = state.records.get(idx as usize).map(|r| r.report.clone());
+                ui.show_comparison(&state);
+            }
+        });
+
+        self.on_reexport_measurement({
+            let ui = self.clone();
+            move |idx, format| {
+                let state = state.lock().expect("Browser state Mutex poisened");
+                let Some(record) = state.records.get(idx as usize) else {
+                    ui.set_measurement_status("No measurement selected.".into());
+                    return;
+                };
+                let output_dir = PathBuf::from(ui.get_output_dir().as_str());
+                let status = match ExportFormat::try_from(format.as_str())
+                    .and_then(|f| record.report.in_units(ui.units()).export(&output_dir, f))
+                {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(e) => format!("Export failed: {e}"),
+                };
+                ui.set_measurement_status(status.into());
+            }
+        });
+    }
+
+    fn show_measurements(&self, db: &MeasurementDb, filter: &MeasurementFilter, state: &Arc<Mutex<BrowserState>>) {
+        let records = match db.query(filter) {
+            Ok(records) => records,
+            Err(e) => {
+                eprintln!("Querying measurements failed: {e}");
+                self.set_measurement_status(format!("Query failed: {e}").into());
+                return;
+            }
+        };
+
+        let items: VecModel<StandardListViewItem> = records
+            .iter()
+            .map(|r| {
+                let report = &r.report;
+                StandardListViewItem::from(SharedString::from(format!(
+                    "#{}  {}  {}  {}x{} @ {}  TN {:.3}  FPN {:.3}  row {:.3}  col {:.3}",
+                    r.id,
+                    report.timestamp,
+                    report.device_path,
+                    report.width,
+                    report.height,
+                    report.framerate,
+                    report.metrics.temporal_noise,
+                    report.metrics.fixed_pattern_noise,
+                    report.metrics.row_noise,
+                    report.metrics.column_noise,
+                )))
+            })
+            .collect();
+        self.set_measurement_records(ModelRc::new(items));
+        self.set_measurement_status(format!("{} measurements", records.len()).into());
+
+        state.lock().expect("Browser state Mutex poisened").records = records;
+    }
+
+    fn show_comparison(&self, state: &BrowserState) {
+        let metric = |r: &Option<MeasurementReport>, f: fn(&MeasurementReport) -> f32| r.as_ref().map(f);
+        let text = |v: Option<f32>| v.map(|v| format!("{v:.3}")).unwrap_or_default();
+        let row = |name: &str, f: fn(&MeasurementReport) -> f32| {
+            let (a, b) = (metric(&state.a, f), metric(&state.b, f));
+            let delta = match (a, b) {
+                (Some(a), Some(b)) if a != 0.0 => format!("{:+.3} ({:+.1} %)", b - a, (b - a) / a * 100.0),
+                (Some(a), Some(b)) => format!("{:+.3}", b - a),
+                _ => String::new(),
+            };
+            CompareRow {
+                name: name.into(),
+                a: text(a).into(),
+                b: text(b).into(),
+                delta: delta.into(),
+            }
+        };
+        let info = |name: &str, f: fn(&MeasurementReport) -> String| CompareRow {
+            name: name.into(),
+            a: state.a.as_ref().map(f).unwrap_or_default().into(),
+            b: state.b.as_ref().map(f).unwrap_or_default().into(),
+            delta: SharedString::new(),
+        };
+
+        let rows = vec![
+            info("Timestamp", |r| r.timestamp.clone()),
+            info("Device", |r| r.device_path.clone()),
+            info("Resolution", |r| format!("{}x{} @ {}", r.width, r.height, r.framerate)),
+            row("Temporal noise", |r| r.metrics.temporal_noise),
+            row("Fixed pattern noise", |r| r.metrics.fixed_pattern_noise),
+            row("Row noise", |r| r.metrics.row_noise),
+            row("Column noise", |r| r.metrics.column_noise),
+        ];
+        self.set_measurement_comparison(ModelRc::new(VecModel::from(rows)));
+    }
+}
+
+/// Parses an optional date, an empty string means no restriction.
+fn parse_date(date: &str) -> Result<Option<NaiveDate>, chrono::ParseError> {
+    let date = date.trim();
+    if date.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map(Some)
+}
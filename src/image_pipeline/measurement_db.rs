@@ -0,0 +1,198 @@
+//! Persistent SQLite database of all completed noise measurements.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::prelude::*;
+use rusqlite::{params, Connection};
+use thiserror::Error;
+
+use crate::image_pipeline::export::MeasurementReport;
+
+/// Environment variable to override the database location of the default path and the settings.
+const DB_PATH_ENV: &str = "VIDEO_NOISE_ANALYZER_DB";
+// upper bound of records shown at once in the history browser
+const MAX_QUERY_RESULTS: i64 = 1000;
+
+#[derive(Debug, Error)]
+pub enum DbError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Restricts a database query, unset fields match all records.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MeasurementFilter {
+    pub device_path: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct StoredMeasurement {
+    pub id: i64,
+    pub report: MeasurementReport,
+}
+
+pub(crate) struct MeasurementDb {
+    conn: Connection,
+}
+
+impl MeasurementDb {
+    /// Returns the database path from the environment, the given path of the settings or the default path in the XDG
+    /// data directory.
+    pub(crate) fn path(configured: Option<&str>) -> PathBuf {
+        if let Some(path) = env::var_os(DB_PATH_ENV) {
+            return PathBuf::from(path);
+        }
+        if let Some(path) = configured {
+            return PathBuf::from(path);
+        }
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("video-noise-analyzer")
+            .join("measurements.sqlite")
+    }
+
+    /// Opens the database at the given path, creating it if it does not exist yet.
+    pub(crate) fn open(path: &Path) -> Result<Self, DbError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS measurements (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                stored_at TEXT NOT NULL,
+                device_path TEXT NOT NULL,
+                width INTEGER NOT NULL,
+                height INTEGER NOT NULL,
+                framerate TEXT NOT NULL,
+                pixel_format TEXT,
+                roi_x INTEGER NOT NULL,
+                roi_y INTEGER NOT NULL,
+                roi_w INTEGER NOT NULL,
+                roi_h INTEGER NOT NULL,
+                frame_count INTEGER NOT NULL,
+                temporal_noise REAL NOT NULL,
+                fixed_pattern_noise REAL NOT NULL,
+                row_noise REAL NOT NULL,
+                column_noise REAL NOT NULL,
+                report TEXT NOT NULL,
+                measured_at INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS measurements_device ON measurements (device_path, timestamp);",
+        )?;
+        Self::add_measured_at(&conn)?;
+
+        Ok(MeasurementDb { conn })
+    }
+
+    // the local timestamps do not sort across daylight saving time changes, measurements are ordered by their UTC time
+    // in milliseconds since the epoch instead, databases created before are migrated
+    fn add_measured_at(conn: &Connection) -> Result<(), DbError> {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('measurements') WHERE name = 'measured_at'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            conn.execute_batch(
+                "ALTER TABLE measurements ADD COLUMN measured_at INTEGER;
+                UPDATE measurements
+                    SET measured_at = CAST(ROUND((julianday(timestamp) - 2440587.5) * 86400000) AS INTEGER);",
+            )?;
+        }
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS measurements_time ON measurements (measured_at);")?;
+        Ok(())
+    }
+
+    /// Stores the measurement and returns its record id.
+    ///
+    /// The full report is stored as JSON next to the queryable columns so that it can be re-exported unchanged.
+    pub(crate) fn insert(&self, report: &MeasurementReport) -> Result<i64, DbError> {
+        self.conn.execute(
+            "INSERT INTO measurements (timestamp, stored_at, device_path, width, height, framerate, pixel_format,
+                roi_x, roi_y, roi_w, roi_h, frame_count,
+                temporal_noise, fixed_pattern_noise, row_noise, column_noise, report, measured_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            params![
+                report.timestamp,
+                Local::now().to_rfc3339(),
+                report.device_path,
+                report.width,
+                report.height,
+                report.framerate,
+                report.pixel_format,
+                report.roi.x as i64,
+                report.roi.y as i64,
+                report.roi.w as i64,
+                report.roi.h as i64,
+                report.frame_count as i64,
+                report.metrics.temporal_noise,
+                report.metrics.fixed_pattern_noise,
+                report.metrics.row_noise,
+                report.metrics.column_noise,
+                serde_json::to_string(report)?,
+                DateTime::parse_from_rfc3339(&report.timestamp)
+                    .map(|timestamp| timestamp.timestamp_millis())
+                    .unwrap_or_else(|_| Utc::now().timestamp_millis()),
+            ],
+        )?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Returns all device paths with stored measurements.
+    pub(crate) fn devices(&self) -> Result<Vec<String>, DbError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT device_path FROM measurements ORDER BY device_path")?;
+        let devices = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(devices)
+    }
+
+    /// Returns the measurements matching the filter, newest first.
+    pub(crate) fn query(&self, filter: &MeasurementFilter) -> Result<Vec<StoredMeasurement>, DbError> {
+        // timestamps are stored as RFC 3339 in local time, so the first 10 characters are the local date
+        let mut stmt = self.conn.prepare(
+            "SELECT id, report FROM measurements
+             WHERE (?1 IS NULL OR device_path = ?1)
+               AND (?2 IS NULL OR substr(timestamp, 1, 10) >= ?2)
+               AND (?3 IS NULL OR substr(timestamp, 1, 10) <= ?3)
+             ORDER BY measured_at DESC, id DESC
+             LIMIT ?4",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![
+                    filter.device_path,
+                    filter.from.map(|d| d.format("%Y-%m-%d").to_string()),
+                    filter.to.map(|d| d.format("%Y-%m-%d").to_string()),
+                    MAX_QUERY_RESULTS,
+                ],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(id, report)| {
+                Ok(StoredMeasurement {
+                    id,
+                    report: serde_json::from_str(&report)?,
+                })
+            })
+            .collect()
+    }
+}
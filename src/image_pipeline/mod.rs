@@ -1,7 +1,35 @@
+mod batch_trigger;
+mod bus;
+mod codec_metrics;
+mod config;
 mod device_caps;
+mod drift_log;
+mod export;
 mod frame_handler;
+mod frame_queue;
+mod frame_timing;
+mod golden_reference;
 mod gstreamer;
 mod gui;
+mod history;
+mod image_sequence;
+mod limits;
+mod measurement_browser;
+mod measurement_db;
+mod multi_camera;
+mod network;
 mod noise;
+mod playback;
+mod recording;
+mod report;
+mod roi_preset;
+mod source;
+mod source_switch;
+mod status_log;
+mod stereo;
+mod synthetic;
+mod units;
+mod v4l2_controls;
 
+pub use config::Settings;
 pub use gstreamer::ImagePipeline;
@@ -0,0 +1,328 @@
+//! GUI glue of the camera panel: additional v4l2 devices analyzed side by side with the main pipeline, e.g. for A/B
+//! comparisons between two camera modules or two firmware versions.
+//!
+//! Every camera has its own pipeline, video tile, noise window and metrics. All cameras share the batch trigger, so
+//! that their noise window stacks start together.
+
+use std::{
+    collections::BTreeMap,
+    sync::{atomic::AtomicUsize, Arc, Mutex},
+};
+
+use gst::State;
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel};
+
+use crate::image_pipeline::{
+    batch_trigger::BatchTrigger,
+    config::Roi,
+    device_caps::RawSourceCaps,
+    frame_handler::FrameHandler,
+    gstreamer::{GstError, ImagePipeline},
+    history::NoiseHistory,
+    limits::Limits,
+    measurement_db::MeasurementDb,
+    noise::{MetricView, Noise, NoiseContext},
+    source::VideoSource,
+    stereo::StereoPairing,
+};
+use crate::{App, CameraCompareRow, CameraTile};
+
+// size of the noise window of a new camera, small enough for every capability
+const DEFAULT_NOISE_WINDOW: i32 = 64;
+// first entry of the stereo partner combo box, followed by the added cameras
+const STEREO_OFF: &str = "off";
+
+/// Shared state the additional cameras are created with.
+pub(crate) struct CameraContext {
+    // the capabilities of the main pipeline, a new camera selects its device in a copy
+    pub caps: Arc<Mutex<RawSourceCaps>>,
+    pub source: Arc<Mutex<VideoSource>>,
+    pub db: Option<Arc<Mutex<MeasurementDb>>>,
+    pub threads: Arc<AtomicUsize>,
+    pub trigger: Arc<BatchTrigger>,
+    pub stereo: Arc<StereoPairing>,
+    // noise windows restored from the settings by device path
+    pub rois: BTreeMap<String, Roi>,
+    pub limits: Arc<Mutex<Limits>>,
+}
+
+struct Camera {
+    id: i32,
+    device: String,
+    pipeline: ImagePipeline,
+}
+
+impl Drop for Camera {
+    fn drop(&mut self) {
+        // ends the noise calculation of the camera, the pipeline stops when it is dropped
+        if let Some(fh) = &self.pipeline.frame_handler {
+            fh.frame_queue.close();
+        }
+    }
+}
+
+#[derive(Default)]
+struct CameraSet {
+    cameras: Vec<Camera>,
+    next_id: i32,
+}
+
+impl App {
+    pub(crate) fn init_camera_panel(self: &Arc<App>, ctx: CameraContext) {
+        self.set_camera_tiles(ModelRc::new(VecModel::<CameraTile>::default()));
+        let ctx = Arc::new(ctx);
+        let cameras = Arc::new(Mutex::new(CameraSet::default()));
+
+        self.on_open_camera_panel({
+            let ui = self.clone();
+            let ctx = ctx.clone();
+            let cameras = cameras.clone();
+            move || {
+                ui.show_camera_devices(&ctx, &cameras.lock().expect("Camera Mutex poisened"));
+                ui.show_camera_comparison();
+                ui.set_show_camera_panel(true);
+            }
+        });
+
+        self.on_close_camera_panel({
+            let ui = self.clone();
+            move || ui.set_show_camera_panel(false)
+        });
+
+        self.on_add_camera({
+            let ui = self.clone();
+            let ctx = ctx.clone();
+            let cameras = cameras.clone();
+            move |device| {
+                let mut cameras = cameras.lock().expect("Camera Mutex poisened");
+                let id = cameras.next_id;
+                cameras.next_id += 1;
+                let status = match ui.open_camera(id, device.as_str(), &ctx) {
+                    Ok(pipeline) => {
+                        cameras.cameras.push(Camera {
+                            id,
+                            device: device.to_string(),
+                            pipeline,
+                        });
+                        format!("Added {device}.")
+                    }
+                    Err(e) => format!("Could not open {device}: {e}"),
+                };
+                println!("{status}");
+                ui.set_camera_status(status.into());
+                ui.show_camera_devices(&ctx, &cameras);
+            }
+        });
+
+        self.on_select_stereo_partner({
+            let ui = self.clone();
+            let ctx = ctx.clone();
+            let cameras = cameras.clone();
+            move |idx| {
+                let cameras = cameras.lock().expect("Camera Mutex poisened");
+                // the first entry turns the stereo mode off
+                let partner = usize::try_from(idx - 1).ok().and_then(|idx| cameras.cameras.get(idx));
+                ctx.stereo.set_partner(partner.map(|camera| camera.id));
+                let status = match partner {
+                    Some(camera) => format!("Pairing {} with the main camera…", camera.device),
+                    None => "Stereo mode off.".to_string(),
+                };
+                ui.set_stereo_status(status.into());
+            }
+        });
+
+        self.on_remove_camera({
+            let ui = self.clone();
+            move |id| {
+                let mut cameras = cameras.lock().expect("Camera Mutex poisened");
+                let Some(idx) = cameras.cameras.iter().position(|camera| camera.id == id) else {
+                    return;
+                };
+                let camera = cameras.cameras.remove(idx);
+                ui.set_camera_status(format!("Removed {}.", camera.device).into());
+                if ctx.stereo.partner() == Some(id) {
+                    ctx.stereo.set_partner(None);
+                    ui.set_stereo_status("Stereo mode off, the paired camera was removed.".into());
+                }
+                drop(camera);
+                ui.remove_camera_tile(id);
+                ui.show_camera_devices(&ctx, &cameras);
+                ui.show_camera_comparison();
+            }
+        });
+
+        self.on_set_camera_roi({
+            let ui = self.clone();
+            move |id, x, y, w, h| {
+                ui.update_camera_tile(id, |tile| {
+                    tile.noise_x = x;
+                    tile.noise_y = y;
+                    tile.noise_w = w;
+                    tile.noise_h = h;
+                })
+            }
+        });
+    }
+
+    // creates the pipeline, tile and noise calculation of a camera and starts playing
+    fn open_camera(self: &Arc<App>, id: i32, device: &str, ctx: &CameraContext) -> Result<ImagePipeline, GstError> {
+        let mut caps = ctx.caps.lock().expect("Caps Mutex poisened").clone();
+        caps.set_device(device)?;
+        let resolution = caps.get_current_resolution();
+        let mut pipeline = ImagePipeline::with_caps(caps)?;
+        let fh = FrameHandler::init_camera(&pipeline.pipeline, self.as_weak(), id);
+        pipeline.frame_handler.replace(fh.clone());
+
+        // the tile has to exist before the first frame is shown, the restored window may be larger than the frames of
+        // the selected capability
+        let roi = ctx
+            .rois
+            .get(device)
+            .copied()
+            .unwrap_or(Roi {
+                x: 0,
+                y: 0,
+                w: DEFAULT_NOISE_WINDOW,
+                h: DEFAULT_NOISE_WINDOW,
+            })
+            .clamped(resolution.width, resolution.height);
+        if let Some(tiles) = self.get_camera_tiles().as_any().downcast_ref::<VecModel<CameraTile>>() {
+            tiles.push(CameraTile {
+                id,
+                device: device.into(),
+                noise_x: roi.x,
+                noise_y: roi.y,
+                noise_w: roi.w,
+                noise_h: roi.h,
+                ..CameraTile::default()
+            });
+        }
+        if let Err(e) = pipeline.set_state(State::Playing) {
+            self.remove_camera_tile(id);
+            return Err(e.into());
+        }
+
+        Noise::new(
+            self.clone(),
+            Arc::new(fh),
+            NoiseContext {
+                pipeline: pipeline.pipeline.clone(),
+                caps: pipeline.caps.clone(),
+                source: pipeline.source.clone(),
+                switcher: pipeline.switcher.clone(),
+                history: Arc::new(Mutex::new(NoiseHistory::default())),
+                drift_log: Arc::new(Mutex::new(None)),
+                db: ctx.db.clone(),
+                threads: ctx.threads.clone(),
+                view: MetricView::Camera(id),
+                trigger: ctx.trigger.clone(),
+                stereo: ctx.stereo.clone(),
+                limits: ctx.limits.clone(),
+                headless: false,
+            },
+        )
+        .expect("Could not start noise calculation.");
+
+        Ok(pipeline)
+    }
+
+    // lists the v4l2 devices that are neither the main source nor an added camera, and the cameras that can be paired
+    // with the main camera
+    fn show_camera_devices(&self, ctx: &CameraContext, cameras: &CameraSet) {
+        let source = ctx.source.lock().expect("Source Mutex poisened").clone();
+        let caps = ctx.caps.lock().expect("Caps Mutex poisened");
+        let main_device = source.v4l2_device_path(&caps);
+        let devices: Vec<SharedString> = caps
+            .get_device_paths()
+            .into_iter()
+            .filter(|path| main_device.as_ref() != Some(path))
+            .filter(|path| !cameras.cameras.iter().any(|camera| &camera.device == path))
+            .map(SharedString::from)
+            .collect();
+        self.set_camera_devices(ModelRc::new(VecModel::from(devices)));
+        self.set_main_camera_device(source.name(&caps).into());
+
+        let partners: Vec<SharedString> = std::iter::once(STEREO_OFF.into())
+            .chain(cameras.cameras.iter().map(|camera| camera.device.as_str().into()))
+            .collect();
+        let partner_idx = ctx
+            .stereo
+            .partner()
+            .and_then(|id| cameras.cameras.iter().position(|camera| camera.id == id))
+            .map_or(0, |idx| idx as i32 + 1);
+        self.set_stereo_partners(ModelRc::new(VecModel::from(partners)));
+        self.set_stereo_partner_index(partner_idx);
+    }
+
+    /// Returns the tile of the camera with the given id, None if the camera was removed.
+    pub(crate) fn camera_tile(&self, id: i32) -> Option<CameraTile> {
+        self.get_camera_tiles().iter().find(|tile| tile.id == id)
+    }
+
+    /// Changes the tile of the camera with the given id, tiles of removed cameras are ignored.
+    pub(crate) fn update_camera_tile(&self, id: i32, update: impl FnOnce(&mut CameraTile)) {
+        let tiles = self.get_camera_tiles();
+        let Some(row) = tiles.iter().position(|tile| tile.id == id) else {
+            return;
+        };
+        let Some(mut tile) = tiles.row_data(row) else {
+            return;
+        };
+        update(&mut tile);
+        tiles.set_row_data(row, tile);
+    }
+
+    fn remove_camera_tile(&self, id: i32) {
+        let tiles = self.get_camera_tiles();
+        if let Some(tiles) = tiles.as_any().downcast_ref::<VecModel<CameraTile>>() {
+            if let Some(row) = tiles.iter().position(|tile| tile.id == id) {
+                tiles.remove(row);
+            }
+        }
+    }
+
+    /// Compares the latest metrics of the additional cameras with the main camera.
+    pub(crate) fn show_camera_comparison(&self) {
+        let tiles: Vec<CameraTile> = self.get_camera_tiles().iter().collect();
+        if tiles.is_empty() {
+            self.set_camera_comparison(ModelRc::default());
+            return;
+        }
+
+        let strings = |values: Vec<String>| {
+            let values: Vec<SharedString> = values.into_iter().map(SharedString::from).collect();
+            ModelRc::new(VecModel::from(values))
+        };
+        let row = |name: &str, main: f32, f: fn(&CameraTile) -> f32| {
+            let mut values = vec![format!("{main:.3}")];
+            values.extend(tiles.iter().map(|tile| {
+                let value = f(tile);
+                if main != 0.0 {
+                    format!("{value:.3} ({:+.1} %)", (value - main) / main * 100.0)
+                } else {
+                    format!("{value:.3}")
+                }
+            }));
+            CameraCompareRow {
+                name: name.into(),
+                values: strings(values),
+            }
+        };
+
+        let mut names = vec![format!("Main {}", self.get_main_camera_device())];
+        names.extend(tiles.iter().map(|tile| tile.device.to_string()));
+        let rows = vec![
+            CameraCompareRow {
+                name: SharedString::new(),
+                values: strings(names),
+            },
+            row("Temporal noise", self.get_temporal_noise(), |t| t.temporal_noise),
+            row("Fixed pattern noise", self.get_fixed_pattern_noise(), |t| {
+                t.fixed_pattern_noise
+            }),
+            row("Row noise", self.get_row_noise(), |t| t.row_noise),
+            row("Column noise", self.get_column_noise(), |t| t.column_noise),
+        ];
+        self.set_camera_comparison(ModelRc::new(VecModel::from(rows)));
+    }
+}
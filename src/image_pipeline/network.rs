@@ -0,0 +1,198 @@
+//! Network stream source for IP cameras: RTSP, RTP over UDP and MPEG-TS over UDP.
+//!
+//! Network streams are compressed, so the analyzed frames are decoded frames and the measured noise includes the
+//! artifacts of the codec. Jitter and packet loss are read from the jitter buffers of the source, frames dropped for
+//! arriving too late from the QoS messages on the bus.
+
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use gst::prelude::*;
+
+use crate::image_pipeline::{bus::BusWatcher, gstreamer::GstError, source::is_video_pad};
+
+/// Default latency of the jitter buffer in milliseconds.
+pub const DEFAULT_LATENCY_MS: u32 = 200;
+
+// decodebin that ends every receiving chain, its decoded pad is linked to the analyzer
+const DECODER: &str = "decodebin name=decoder";
+
+pub const CODEC_WARNING: &str = "Decoded network stream: codec artifacts affect the noise values.";
+
+/// Network stream given by its URL:
+/// - `rtsp://host[:port]/path` RTSP stream, the codec is negotiated by the server
+/// - `rtp://host:port?encoding=H264` RTP over UDP, encoding is one of H264, H265, JPEG or MP2T, default H264
+/// - `udp://host:port` MPEG-TS over UDP
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkStream {
+    pub url: String,
+    pub latency_ms: u32,
+}
+
+impl Display for NetworkStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.url)
+    }
+}
+
+impl NetworkStream {
+    /// Creates a bin named "source" that receives, depayloads and decodes the stream.
+    ///
+    /// The decoded video pad appears once the stream is received and is linked to the sink element then.
+    pub(crate) fn create_source(&self, sink: &gst::Element) -> Result<gst::Element, GstError> {
+        let description = self.description()?;
+        println!("Opening network stream: {description}");
+
+        let bin = gst::parse::bin_from_description(&description, false)
+            .map_err(|e| GstError::InvalidDescription(e.message().to_string()))?;
+        bin.set_property("name", "source");
+        let decoder = bin.by_name("decoder").ok_or(GstError::ElementNotFound)?;
+
+        // the decoded pad is exposed as ghost pad of the bin, only the first video stream is analyzed
+        let bin_weak = bin.downgrade();
+        let sink_weak = sink.downgrade();
+        decoder.connect_pad_added(move |_, src_pad| {
+            let (Some(bin), Some(sink)) = (bin_weak.upgrade(), sink_weak.upgrade()) else {
+                return;
+            };
+            let sink_pad = sink.static_pad("sink").expect("Converter has no sink pad.");
+            if sink_pad.is_linked() || !is_video_pad(src_pad) {
+                return;
+            }
+            let linked = gst::GhostPad::with_target(src_pad).and_then(|ghost| {
+                ghost.set_active(true)?;
+                bin.add_pad(&ghost)?;
+                ghost.link(&sink_pad).map_err(|e| gst::glib::bool_error!("{e}"))
+            });
+            if let Err(e) = linked {
+                eprintln!("Could not link decoded network stream: {e}");
+            }
+        });
+
+        Ok(bin.upcast())
+    }
+
+    /// Returns the gst-launch description of the receiving elements, ending with a decodebin named "decoder".
+    fn description(&self) -> Result<String, GstError> {
+        let invalid = || GstError::InvalidUrl(self.url.clone());
+        let (scheme, rest) = self.url.trim().split_once("://").ok_or_else(invalid)?;
+        let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+        if address.is_empty() {
+            return Err(invalid());
+        }
+
+        match scheme.to_ascii_lowercase().as_str() {
+            "rtsp" | "rtsps" => Ok(format!(
+                "rtspsrc location=\"{}\" latency={} ! {DECODER}",
+                self.url.trim(),
+                self.latency_ms
+            )),
+            "rtp" => {
+                let encoding = query
+                    .split('&')
+                    .find_map(|param| param.strip_prefix("encoding="))
+                    .unwrap_or("H264")
+                    .to_ascii_uppercase();
+                let depayloader = match encoding.as_str() {
+                    "H264" => "rtph264depay ! h264parse",
+                    "H265" => "rtph265depay ! h265parse",
+                    "JPEG" => "rtpjpegdepay",
+                    "MP2T" => "rtpmp2tdepay",
+                    _ => return Err(invalid()),
+                };
+                let caps = format!("application/x-rtp,media=video,clock-rate=90000,encoding-name={encoding}");
+                let jitter_buffer = format!("rtpjitterbuffer latency={}", self.latency_ms);
+                Ok(format!(
+                    "udpsrc uri=udp://{address} caps=\"{caps}\" ! {jitter_buffer} ! {depayloader} ! {DECODER}"
+                ))
+            }
+            "udp" => Ok(format!("udpsrc uri=udp://{address} ! {DECODER}")),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Transport statistics of a network stream, counted since the stream was opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct NetworkStats {
+    pub packets_received: u64,
+    pub packets_lost: u64,
+    pub packets_late: u64,
+    /// highest average interarrival jitter of all jitter buffers in milliseconds
+    pub jitter_ms: f64,
+    /// frames dropped by the pipeline for arriving too late
+    pub frames_dropped: u64,
+}
+
+impl Display for NetworkStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "packets: {} received, {} lost, {} late, jitter: {:.2} ms, dropped frames: {}",
+            self.packets_received, self.packets_lost, self.packets_late, self.jitter_ms, self.frames_dropped
+        )
+    }
+}
+
+pub(crate) struct NetworkMonitor {
+    pipeline: gst::Pipeline,
+    frames_dropped: Arc<AtomicU64>,
+}
+
+impl NetworkMonitor {
+    /// Starts counting the frames dropped by the pipeline.
+    pub(crate) fn new(pipeline: gst::Pipeline, bus: &BusWatcher) -> Self {
+        let frames_dropped = Arc::new(AtomicU64::new(0));
+        let qos_dropped = frames_dropped.clone();
+        bus.add_handler(move |msg| {
+            // every QoS message reports a buffer that was dropped or arrived too late
+            if let gst::MessageView::Qos(_) = msg.view() {
+                qos_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        NetworkMonitor {
+            pipeline,
+            frames_dropped,
+        }
+    }
+
+    /// Resets the statistics when a new stream is opened.
+    pub(crate) fn reset(&self) {
+        self.frames_dropped.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the statistics of the jitter buffers of the source, None if the source has no jitter buffer.
+    pub(crate) fn stats(&self) -> Option<NetworkStats> {
+        let source = self.pipeline.by_name("source")?.downcast::<gst::Bin>().ok()?;
+        let mut stats = NetworkStats {
+            frames_dropped: self.frames_dropped.load(Ordering::Relaxed),
+            ..NetworkStats::default()
+        };
+
+        // rtspsrc creates one jitter buffer per stream inside its rtpbin
+        let mut found = false;
+        for element in source.iterate_recurse().into_iter().flatten() {
+            if element
+                .factory()
+                .is_none_or(|factory| factory.name().as_str() != "rtpjitterbuffer")
+            {
+                continue;
+            }
+            let jitter_stats = element.property::<gst::Structure>("stats");
+            stats.packets_received += jitter_stats.get::<u64>("num-pushed").unwrap_or(0);
+            stats.packets_lost += jitter_stats.get::<u64>("num-lost").unwrap_or(0);
+            stats.packets_late += jitter_stats.get::<u64>("num-late").unwrap_or(0);
+            let jitter_ns = jitter_stats.get::<u64>("avg-jitter").unwrap_or(0);
+            stats.jitter_ms = stats.jitter_ms.max(jitter_ns as f64 / 1e6);
+            found = true;
+        }
+
+        found.then_some(stats)
+    }
+}
@@ -1,28 +1,86 @@
 use std::{
     sync::{
+        atomic::{AtomicUsize, Ordering},
         mpsc::{self, Receiver},
-        Arc,
+        Arc, Mutex,
     },
     thread,
 };
 
-use crate::{image_pipeline::frame_handler::FrameHandler, App};
+use crate::{
+    image_pipeline::{
+        batch_trigger::BatchTrigger,
+        codec_metrics::calc_codec_metrics,
+        device_caps::RawSourceCaps,
+        drift_log::DriftLogger,
+        export::{MeasurementReport, Metrics, SourceMetadata},
+        frame_handler::FrameHandler,
+        frame_timing::{FrameTiming, FrameTimingStats},
+        gstreamer::ImagePipeline,
+        history::{NoiseHistory, NoiseResult},
+        limits::Limits,
+        measurement_db::MeasurementDb,
+        source::VideoSource,
+        source_switch::SourceSwitcher,
+        status_log::{LogEntry, LogLevel},
+        stereo::{StereoPairing, StereoSample},
+        v4l2_controls::ControlCache,
+    },
+    App,
+};
 
-use ndarray::prelude::*;
-use slint::{ComponentHandle, Rgb8Pixel, SharedPixelBuffer, Weak};
+use chrono::Local;
+use image::RgbImage;
+use ndarray::{parallel::prelude::*, prelude::*, Zip};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use slint::{ComponentHandle, Weak};
 
 pub(crate) struct Noise {
     ui: Arc<App>,
     fh: Arc<FrameHandler>,
+    ctx: NoiseContext,
+}
+
+/// Shared state the noise metric calculation reads from and hands its results to.
+#[derive(Clone)]
+pub(crate) struct NoiseContext {
+    pub pipeline: gst::Pipeline,
+    pub caps: Arc<Mutex<RawSourceCaps>>,
+    pub source: Arc<Mutex<VideoSource>>,
+    // frames collected before a source switch are discarded
+    pub switcher: Arc<SourceSwitcher>,
+    pub history: Arc<Mutex<NoiseHistory>>,
+    pub drift_log: Arc<Mutex<Option<DriftLogger>>>,
+    // None if the measurement database could not be opened
+    pub db: Option<Arc<Mutex<MeasurementDb>>>,
+    /// number of worker threads of the metric calculation, 0 uses one thread per core
+    pub threads: Arc<AtomicUsize>,
+    pub view: MetricView,
+    // the noise window stacks of all cameras start together
+    pub trigger: Arc<BatchTrigger>,
+    pub stereo: Arc<StereoPairing>,
+    // the metrics are checked against the limits of the production test
+    pub limits: Arc<Mutex<Limits>>,
+    // the first measurement of the main camera ends a headless run
+    pub headless: bool,
 }
 
-#[derive(Default, PartialEq, Clone, Copy)]
-struct NoiseConfig {
-    x: usize,
-    y: usize,
-    w: usize,
-    h: usize,
-    n_frames: usize,
+/// GUI elements the noise window is read from and the metrics are shown in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MetricView {
+    /// the main video window, metric grid and history chart
+    Main,
+    /// the tile of the additional camera with the given id
+    Camera(i32),
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub(crate) struct NoiseConfig {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+    pub n_frames: usize,
 }
 
 #[derive(Debug)]
@@ -34,8 +92,47 @@ struct FixedPatternNoise {
 
 struct TemporalNoise(f32);
 
-type NoiseWindow = Array2<f32>;
+/// Per-pixel and per-line statistics of a noise window stack, e.g. for heat maps and profile plots.
+#[derive(Debug, Clone)]
+pub(crate) struct NoiseMaps {
+    pub temporal_mean: Array2<f32>,
+    pub temporal_std: Array2<f32>,
+    pub row_profile: Array1<f32>,
+    pub col_profile: Array1<f32>,
+}
+
+// deviation of the temporal mean of a pixel from the median level of the noise window in DN above which the pixel
+// counts as hot or dead
+const DEFECT_THRESHOLD: f32 = 40.0;
+
 type NoiseWindowStack = Array3<f32>;
+// a full noise window stack with the timing of its frames, the source they were taken from and a snapshot of its last
+// frame for the report, the snapshot is only taken for the main camera
+type CompletedStack = (
+    NoiseConfig,
+    NoiseWindowStack,
+    FrameTimingStats,
+    SourceMetadata,
+    Option<RgbImage>,
+);
+
+/// Size and row stride in bytes of a packed RGB frame.
+#[derive(Debug, Clone, Copy)]
+struct FrameLayout {
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl From<&gst_video::VideoInfo> for FrameLayout {
+    fn from(info: &gst_video::VideoInfo) -> Self {
+        FrameLayout {
+            width: info.width() as usize,
+            height: info.height() as usize,
+            stride: info.stride()[0] as usize,
+        }
+    }
+}
 
 impl From<NoiseConfig> for NoiseWindowStack {
     fn from(value: NoiseConfig) -> Self {
@@ -50,145 +147,692 @@ impl NoiseConfig {
 }
 
 impl Noise {
-    pub(crate) fn new(ui: Arc<App>, fh: Arc<FrameHandler>) -> Result<(), ()> {
-        let noise = Self { ui, fh };
+    pub(crate) fn new(ui: Arc<App>, fh: Arc<FrameHandler>, ctx: NoiseContext) -> Result<(), ()> {
+        let noise = Self { ui, fh, ctx };
         noise.start_calculation();
         Ok(())
     }
 
     fn start_calculation(&self) {
         let ui_weak = self.ui.as_weak();
-        let new_frame_rx = self.fh.frame_rx.clone();
-        let (win_stack_tx, win_stack_rx) = mpsc::channel::<NoiseWindowStack>();
+        let frame_queue = self.fh.frame_queue.clone();
+        let (win_stack_tx, win_stack_rx) = mpsc::channel::<CompletedStack>();
+
+        let switcher = self.ctx.switcher.clone();
+        let view = self.ctx.view;
+        let trigger = self.ctx.trigger.join();
+        let stereo = self.ctx.stereo.clone();
+        let ctx = self.ctx.clone();
 
-        Self::start_noise_metric_calculation_loop(win_stack_rx, ui_weak.clone());
+        Self::start_noise_metric_calculation_loop(win_stack_rx, ui_weak.clone(), self.ctx.clone());
 
         let calculation_loop = move || {
-            let mut curr_noise_cfg = Self::get_window_dims(&ui_weak).expect("Did not get noise window dimensions.");
+            let mut curr_noise_cfg =
+                Self::get_window_dims(&ui_weak, view).expect("Did not get noise window dimensions.");
+            let mut curr_generation = switcher.generation();
+            let mut timing = FrameTiming::default();
+            let mut dropped_at_start = frame_queue.dropped();
+            let mut block_timeouts_at_start = frame_queue.block_timeouts();
+            let mut frame_error = false;
+            let mut controls = ControlCache::default();
 
             loop {
+                // frames that arrived while waiting for the other cameras are older than the batch
+                if trigger.wait() {
+                    frame_queue.clear();
+                    timing = FrameTiming::default();
+                }
+
                 let mut win_stack = NoiseWindowStack::from(curr_noise_cfg);
+                let mut snapshot = None;
                 let mut iter_idx = 0;
                 while iter_idx < curr_noise_cfg.n_frames {
-                    // wait for new frame
-                    let pixbuf = new_frame_rx
-                        .lock()
-                        .expect("Locking new frame signal failed.")
-                        .recv()
-                        .expect("Unable to receive new frame signal.");
-
-                    let noise_cfg = Self::get_window_dims(&ui_weak).expect("Did not get noise window dimensions.");
+                    // wait for new frame, the queue is closed when the camera is removed
+                    let Some(frame) = frame_queue.pop() else {
+                        return;
+                    };
+
+                    let noise_cfg =
+                        Self::get_window_dims(&ui_weak, view).expect("Did not get noise window dimensions.");
                     if !noise_cfg.is_valid() {
                         continue;
                     }
 
-                    // reset noise window stack if noise config changed
-                    if noise_cfg != curr_noise_cfg {
+                    // reset noise window stack if noise config or source changed
+                    let generation = switcher.generation();
+                    if noise_cfg != curr_noise_cfg || generation != curr_generation {
                         curr_noise_cfg = noise_cfg;
+                        curr_generation = generation;
+                        controls.clear();
                         win_stack = NoiseWindowStack::from(curr_noise_cfg);
                         iter_idx = 0;
+                        timing = FrameTiming::default();
+                    }
+                    timing.add(frame.timestamp);
+                    // frames dropped by the queue before the first frame of a stack do not affect the stack
+                    if iter_idx == 0 {
+                        dropped_at_start = frame_queue.dropped();
+                        block_timeouts_at_start = frame_queue.block_timeouts();
                     }
 
                     // update noise frame count
                     ui_weak
-                        .upgrade_in_event_loop(move |ui| ui.set_noise_framecount(iter_idx as f32 + 1.0))
+                        .upgrade_in_event_loop(move |ui| match view {
+                            MetricView::Main => ui.set_noise_framecount(iter_idx as f32 + 1.0),
+                            MetricView::Camera(id) => {
+                                ui.update_camera_tile(id, |tile| tile.framecount = iter_idx as i32 + 1)
+                            }
+                        })
                         .expect("Upgrading UI failed.");
 
-                    // write window into window stack
-                    win_stack
-                        .slice_mut(s![.., .., iter_idx])
-                        .assign(&Self::slice_out_window(pixbuf, &noise_cfg).expect("Could not retrieve noise window."));
+                    // write window into window stack, the buffer of the frame is read without copying it
+                    let read = frame
+                        .buffer
+                        .map_readable()
+                        .map_err(|e| format!("Could not map frame buffer: {e}"))
+                        .and_then(|map| {
+                            Self::read_window(
+                                map.as_slice(),
+                                FrameLayout::from(&frame.info),
+                                &noise_cfg,
+                                win_stack.slice_mut(s![.., .., iter_idx]),
+                            )
+                        });
+                    // e.g. a frame of a new capability that arrived before the noise window was adapted, the stack
+                    // starts over and the problem is reported once until a frame is read again
+                    if let Err(e) = read {
+                        if !frame_error {
+                            frame_error = true;
+                            ui_weak
+                                .upgrade_in_event_loop(move |ui| {
+                                    ui.add_log_entry(LogEntry::new(LogLevel::Warning, format!("Frame skipped: {e}")))
+                                })
+                                .expect("Upgrading UI failed.");
+                        }
+                        win_stack = NoiseWindowStack::from(curr_noise_cfg);
+                        iter_idx = 0;
+                        timing = FrameTiming::default();
+                        continue;
+                    }
+                    frame_error = false;
+                    trigger.frame_added();
+
+                    // the stereo mode correlates the windows of two cameras frame by frame
+                    if let Some(capture_time) = frame.timestamp.capture_time.filter(|_| stereo.is_paired(view)) {
+                        let row_means = win_stack
+                            .slice(s![.., .., iter_idx])
+                            .mean_axis(Axis(1))
+                            .expect("Calculating row means failed.");
+                        stereo.push(
+                            view,
+                            StereoSample {
+                                capture_time,
+                                nominal_interval: frame.timestamp.nominal_interval,
+                                n_frames: noise_cfg.n_frames,
+                                roi_mean: row_means.mean().unwrap_or_default(),
+                                row_means,
+                            },
+                        );
+                    }
+                    // the report shows the frame the measurement ended with, not the frame shown when it is created
+                    if view == MetricView::Main && iter_idx + 1 == curr_noise_cfg.n_frames {
+                        snapshot = frame.to_rgb_image();
+                    }
                     iter_idx += 1;
                 }
                 // calculate noise metrics for each full window stack
-                win_stack_tx.send(win_stack).unwrap();
+                let timing_stats = FrameTimingStats {
+                    skipped_frames: frame_queue.dropped() - dropped_at_start,
+                    block_timeouts: frame_queue.block_timeouts() - block_timeouts_at_start,
+                    ..timing.take_stats()
+                };
+                // the metadata belongs to the frames of the stack, not to the source at the time of an export
+                let source = ctx.source.lock().expect("Source Mutex poisened").clone();
+                let metadata = SourceMetadata::capture(
+                    &source,
+                    &ctx.caps.lock().expect("Caps Mutex poisened"),
+                    ImagePipeline::get_stream_info(&ctx.pipeline),
+                    &mut controls,
+                );
+                win_stack_tx
+                    .send((curr_noise_cfg, win_stack, timing_stats, metadata, snapshot))
+                    .unwrap();
             }
         };
 
         thread::spawn(move || calculation_loop());
     }
 
-    fn get_window_dims(ui: &Weak<App>) -> Result<NoiseConfig, ()> {
+    fn get_window_dims(ui: &Weak<App>, view: MetricView) -> Result<NoiseConfig, ()> {
         let (tx, rx) = mpsc::channel::<NoiseConfig>();
         ui.upgrade_in_event_loop(move |ui| {
-            tx.send(NoiseConfig {
-                x: ui.get_noise_x() as usize,
-                y: ui.get_noise_y() as usize,
-                w: ui.get_noise_w() as usize,
-                h: ui.get_noise_h() as usize,
-                n_frames: ui.get_number_of_frames() as usize,
-            })
-            .expect("Could not send noise window size.");
+            let n_frames = ui.get_number_of_frames() as usize;
+            let config = match view {
+                MetricView::Main => NoiseConfig {
+                    x: ui.get_noise_x() as usize,
+                    y: ui.get_noise_y() as usize,
+                    w: ui.get_noise_w() as usize,
+                    h: ui.get_noise_h() as usize,
+                    n_frames,
+                },
+                // a removed camera has no noise window
+                MetricView::Camera(id) => ui
+                    .camera_tile(id)
+                    .map(|tile| NoiseConfig {
+                        x: tile.noise_x.max(0) as usize,
+                        y: tile.noise_y.max(0) as usize,
+                        w: tile.noise_w.max(0) as usize,
+                        h: tile.noise_h.max(0) as usize,
+                        n_frames,
+                    })
+                    .unwrap_or_default(),
+            };
+            tx.send(config).expect("Could not send noise window size.");
         })
         .expect("Could not upgrade GUI.");
 
         Ok(rx.recv().expect("Could not receive noise window size."))
     }
 
-    // queries noise window dimensions from GUI and slices out the window from the provided pixbuf
-    fn slice_out_window(pixbuf: SharedPixelBuffer<Rgb8Pixel>, noise_win: &NoiseConfig) -> Result<NoiseWindow, ()> {
-        // convert into Array
-        let arr = Array3::<u8>::from_shape_vec(
-            (pixbuf.height() as usize, pixbuf.width() as usize, 3 as usize),
-            pixbuf.as_bytes().to_vec(),
-        )
-        .expect("Could not convert pixel buffer to ArrayView.");
-        let arr = arr.mapv(|v| v as f32);
-
-        // slice out and return noise window and convert to luminance
-        let noise_x_end = (noise_win.x + noise_win.w) as usize;
-        let noise_y_end = (noise_win.y + noise_win.h) as usize;
-        if arr.shape()[0] < noise_y_end || arr.shape()[1] < noise_x_end {
-            eprintln!("Noise window too large for input image.");
-            return Err(());
+    // converts the pixels of the noise window to luminance and writes them into the window of the stack, the rest of
+    // the frame is not touched
+    fn read_window(
+        data: &[u8],
+        layout: FrameLayout,
+        noise_win: &NoiseConfig,
+        mut window: ArrayViewMut2<f32>,
+    ) -> Result<(), String> {
+        let noise_x_end = noise_win.x + noise_win.w;
+        let noise_y_end = noise_win.y + noise_win.h;
+        if layout.height < noise_y_end || layout.width < noise_x_end {
+            return Err(format!(
+                "Noise window {}x{} at {},{} too large for {}x{} frame.",
+                noise_win.w, noise_win.h, noise_win.x, noise_win.y, layout.width, layout.height
+            ));
+        }
+        if data.len() < (noise_y_end - 1) * layout.stride + noise_x_end * 3 {
+            return Err("Frame buffer smaller than its size.".to_string());
         }
 
-        let red = arr.slice(s![noise_win.y..noise_y_end, noise_win.x..noise_x_end, 0]);
-        let green = arr.slice(s![noise_win.y..noise_y_end, noise_win.x..noise_x_end, 1]);
-        let blue = arr.slice(s![noise_win.y..noise_y_end, noise_win.x..noise_x_end, 2]);
-
-        // Y conversion according to BT.601
-        Ok(0.299 * &red + 0.587 * &green + 0.114 * &blue)
+        for (row, mut window_row) in window.outer_iter_mut().enumerate() {
+            let start = (noise_win.y + row) * layout.stride + noise_win.x * 3;
+            let pixels = &data[start..start + noise_win.w * 3];
+            for (value, rgb) in window_row.iter_mut().zip(pixels.chunks_exact(3)) {
+                // Y conversion according to BT.601
+                *value = 0.299 * rgb[0] as f32 + 0.587 * rgb[1] as f32 + 0.114 * rgb[2] as f32;
+            }
+        }
+        Ok(())
     }
 
     // calculates noise metrics and updates GUI values in a separate thread
-    fn start_noise_metric_calculation_loop(win_stack_rx: Receiver<NoiseWindowStack>, ui: Weak<App>) {
-        thread::spawn(move || loop {
-            let win_stack = win_stack_rx.recv().unwrap();
-            let fpn = Self::calc_fixed_pattern_noise(&win_stack);
-            let tn = Self::calc_temporal_noise(&win_stack);
-            ui.upgrade_in_event_loop(move |ui| {
-                ui.set_temporal_noise(tn.0);
-                ui.set_fixed_pattern_noise(fpn.fpn);
-                ui.set_row_noise(fpn.row);
-                ui.set_column_noise(fpn.col);
-            })
-            .expect("UI could not be upgraded.");
+    fn start_noise_metric_calculation_loop(win_stack_rx: Receiver<CompletedStack>, ui: Weak<App>, ctx: NoiseContext) {
+        let mut pool: Option<ThreadPool> = None;
+        let mut pool_threads = 0;
+        // the loop ends with the collection loop of its camera
+        thread::spawn(move || {
+            while let Ok((config, win_stack, timing, source, snapshot)) = win_stack_rx.recv() {
+                let threads = ctx.threads.load(Ordering::Relaxed);
+                if pool.is_none() || threads != pool_threads {
+                    pool = Self::build_worker_pool(threads);
+                    pool_threads = threads;
+                }
+                let calc_metrics = || {
+                    let maps = Self::calc_noise_maps(&win_stack);
+                    let fpn = Self::calc_fixed_pattern_noise(&win_stack, &maps);
+                    let tn = Self::calc_temporal_noise(&maps);
+                    (fpn, tn, maps)
+                };
+                let (fpn, tn, maps) = match &pool {
+                    Some(pool) => pool.install(calc_metrics),
+                    None => calc_metrics(),
+                };
+                let codec = calc_codec_metrics(&win_stack, config.x, config.y);
+                let snr = Self::calc_snr(&maps, &tn);
+                let defect_pixels = Self::calc_defect_pixels(&maps);
+
+                let result = NoiseResult {
+                    timestamp: Local::now(),
+                    config,
+                    temporal: tn.0,
+                    fpn: fpn.fpn,
+                    row: fpn.row,
+                    col: fpn.col,
+                    snr,
+                    defect_pixels,
+                    timing,
+                    source,
+                };
+
+                let limits = *ctx.limits.lock().expect("Limits Mutex poisened");
+                let verdict = limits.check(&Metrics::from(&result));
+
+                if let Some(logger) = ctx.drift_log.lock().expect("Drift log Mutex poisened").as_mut() {
+                    logger.log(&result);
+                }
+
+                if let Some(db) = &ctx.db {
+                    let report = MeasurementReport::new(&result, &limits);
+                    if let Err(e) = db.lock().expect("Database Mutex poisened").insert(&report) {
+                        eprintln!("Storing measurement in database failed: {e}");
+                    }
+                }
+
+                // keep every result of the main camera in the history and redraw the history chart
+                let view = ctx.view;
+                let headless = ctx.headless;
+                let chart = (view == MetricView::Main).then(|| {
+                    let mut history = ctx.history.lock().expect("History Mutex poisened");
+                    history.push(result, maps, snapshot);
+                    history.chart()
+                });
+
+                ui.upgrade_in_event_loop(move |ui| {
+                    match view {
+                        MetricView::Main => {
+                            ui.set_temporal_noise(tn.0);
+                            ui.set_fixed_pattern_noise(fpn.fpn);
+                            ui.set_row_noise(fpn.row);
+                            ui.set_column_noise(fpn.col);
+                            ui.set_snr(snr.unwrap_or(-1.0));
+                            ui.set_defect_pixels(defect_pixels as i32);
+                            ui.set_blockiness(codec.blockiness);
+                            ui.set_ringing(codec.ringing);
+                            ui.set_effective_fps(timing.effective_fps as f32);
+                            ui.set_frame_jitter(timing.interval_jitter_ms as f32);
+                            ui.set_frame_interval_min(timing.interval_min_ms as f32);
+                            ui.set_frame_interval_max(timing.interval_max_ms as f32);
+                            ui.set_dropped_frames(timing.dropped_frames as i32);
+                            ui.set_duplicated_frames(timing.duplicated_frames as i32);
+                            ui.set_skipped_frames(timing.skipped_frames as i32);
+                            ui.set_block_timeouts(timing.block_timeouts as i32);
+                            ui.set_interval_histogram(timing.histogram_string().into());
+                            ui.show_verdict(verdict.as_ref());
+                            if let Some(chart) = chart {
+                                ui.show_history_chart(chart);
+                            }
+                            // every measurement is compared with a loaded golden reference
+                            if !ui.get_reference_path().is_empty() {
+                                ui.invoke_compare_reference();
+                            }
+                            if headless {
+                                let _ = slint::quit_event_loop();
+                            }
+                        }
+                        MetricView::Camera(id) => ui.update_camera_tile(id, |tile| {
+                            tile.temporal_noise = tn.0;
+                            tile.fixed_pattern_noise = fpn.fpn;
+                            tile.row_noise = fpn.row;
+                            tile.column_noise = fpn.col;
+                            tile.skipped_frames = timing.skipped_frames as i32;
+                        }),
+                    }
+                    // the comparison follows the latest metrics of every camera
+                    ui.show_camera_comparison();
+                })
+                .expect("UI could not be upgraded.");
+            }
         });
     }
 
-    fn calc_fixed_pattern_noise(win_stack: &NoiseWindowStack) -> FixedPatternNoise {
-        let temporal_mean = win_stack.mean_axis(Axis(2)).expect("Temporal mean calculation failed.");
+    // builds the thread pool of the metric calculation, 0 threads use one thread per core
+    fn build_worker_pool(threads: usize) -> Option<ThreadPool> {
+        match ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|idx| format!("noise-worker-{idx}"))
+            .build()
+        {
+            Ok(pool) => {
+                println!("Calculating noise metrics with {} threads.", pool.current_num_threads());
+                Some(pool)
+            }
+            Err(e) => {
+                eprintln!("Could not create noise worker threads, calculating on the global thread pool: {e}");
+                None
+            }
+        }
+    }
+
+    fn calc_fixed_pattern_noise(win_stack: &NoiseWindowStack, maps: &NoiseMaps) -> FixedPatternNoise {
+        let fpn = maps.temporal_mean.std(0.0);
+        let row = Self::calc_line_means(win_stack, Axis(0)).std(0.0);
+        let col = Self::calc_line_means(win_stack, Axis(1)).std(0.0);
 
-        let fpn = temporal_mean.std(0.0);
-        let row = win_stack
+        FixedPatternNoise { fpn, row, col }
+    }
+
+    // means of every row (Axis(0)) or column (Axis(1)) in every frame, the lines are averaged in parallel
+    fn calc_line_means(win_stack: &NoiseWindowStack, axis: Axis) -> Array2<f32> {
+        let means: Vec<f32> = win_stack
+            .axis_iter(axis)
+            .into_par_iter()
+            .flat_map_iter(|line| line.mean_axis(Axis(0)).expect("Calculating line mean failed.").to_vec())
+            .collect();
+        Array2::from_shape_vec((win_stack.len_of(axis), win_stack.len_of(Axis(2))), means)
+            .expect("Line means do not match the window stack.")
+    }
+
+    // the temporal statistics of all pixels are calculated in parallel
+    fn calc_noise_maps(win_stack: &NoiseWindowStack) -> NoiseMaps {
+        let temporal_mean =
+            Zip::from(win_stack.lanes(Axis(2))).par_map_collect(|pixel| pixel.mean().unwrap_or_default());
+        let temporal_std = Zip::from(win_stack.lanes(Axis(2))).par_map_collect(|pixel| pixel.std(0.0));
+        let row_profile = temporal_mean
             .mean_axis(Axis(1))
-            .expect("Calculating column mean failed.")
-            .std(0.0);
-        let col = win_stack
+            .expect("Calculating row profile failed.");
+        let col_profile = temporal_mean
             .mean_axis(Axis(0))
-            .expect("Calculating row mean failed.")
-            .std(0.0);
+            .expect("Calculating column profile failed.");
 
-        FixedPatternNoise { fpn, row, col }
+        NoiseMaps {
+            temporal_mean,
+            temporal_std,
+            row_profile,
+            col_profile,
+        }
     }
 
-    fn calc_temporal_noise(win_stack: &NoiseWindowStack) -> TemporalNoise {
-        let temporal_std = win_stack.std_axis(Axis(2), 0.0);
+    // mean level of the window divided by its temporal noise, None if there is no temporal noise
+    fn calc_snr(maps: &NoiseMaps, tn: &TemporalNoise) -> Option<f32> {
+        (tn.0 > 0.0).then(|| maps.temporal_mean.mean().unwrap_or_default() / tn.0)
+    }
+
+    // pixels whose temporal mean deviates from the median level of the window by more than DEFECT_THRESHOLD, unlike the
+    // mean the median is not shifted by the defect pixels themselves
+    fn calc_defect_pixels(maps: &NoiseMaps) -> usize {
+        let mut levels: Vec<f32> = maps.temporal_mean.iter().copied().collect();
+        if levels.is_empty() {
+            return 0;
+        }
+        let mid = levels.len() / 2;
+        let median = *levels.select_nth_unstable_by(mid, f32::total_cmp).1;
+        maps.temporal_mean
+            .iter()
+            .filter(|&&level| (level - median).abs() > DEFECT_THRESHOLD)
+            .count()
+    }
+
+    fn calc_temporal_noise(maps: &NoiseMaps) -> TemporalNoise {
         TemporalNoise(
-            temporal_std
+            maps.temporal_std
                 .mean()
                 .expect("Mean of temporal std could not be calculated."),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{hint::black_box, time::Instant};
+
+    use super::*;
+    use crate::image_pipeline::synthetic::{SyntheticGenerator, SyntheticParams, FLICKER_PERIOD};
+    use slint::{Rgb8Pixel, SharedPixelBuffer};
+
+    // a multiple of the flicker period, so that the flicker has exactly its standard deviation
+    const N_FRAMES: usize = 10 * FLICKER_PERIOD as usize;
+
+    fn flat() -> SyntheticParams {
+        SyntheticParams {
+            width: 64,
+            height: 64,
+            level: 128.0,
+            temporal_sigma: 0.0,
+            fpn_sigma: 0.0,
+            row_banding: 0.0,
+            column_banding: 0.0,
+            hot_pixels: 0,
+            flicker: 0.0,
+            seed: 7,
+        }
+    }
+
+    // runs the synthetic frames through the same window slicing as the camera frames
+    fn window_stack(params: SyntheticParams) -> NoiseWindowStack {
+        let config = NoiseConfig {
+            x: 0,
+            y: 0,
+            w: params.width as usize,
+            h: params.height as usize,
+            n_frames: N_FRAMES,
+        };
+        let layout = FrameLayout {
+            width: params.width as usize,
+            height: params.height as usize,
+            stride: params.width as usize * 3,
+        };
+        let mut generator = SyntheticGenerator::new(params);
+        let mut win_stack = NoiseWindowStack::from(config);
+        for idx in 0..N_FRAMES {
+            // videoconvert converts gray frames to RGB with equal channels
+            let rgb: Vec<u8> = generator.next_frame().iter().flat_map(|&v| [v, v, v]).collect();
+            Noise::read_window(&rgb, layout, &config, win_stack.slice_mut(s![.., .., idx])).unwrap();
+        }
+        win_stack
+    }
+
+    // runs the synthetic frames through the same metric calculation as the camera frames
+    fn analyze(params: SyntheticParams) -> (TemporalNoise, FixedPatternNoise) {
+        let win_stack = window_stack(params);
+        let maps = Noise::calc_noise_maps(&win_stack);
+        (
+            Noise::calc_temporal_noise(&maps),
+            Noise::calc_fixed_pattern_noise(&win_stack, &maps),
+        )
+    }
+
+    fn assert_close(measured: f32, expected: f32, rel_tolerance: f32) {
+        assert!(
+            (measured - expected).abs() <= expected * rel_tolerance,
+            "measured {measured}, expected {expected} ± {} %",
+            rel_tolerance * 100.0
+        );
+    }
+
+    #[test]
+    fn flat_frames_have_no_noise() {
+        let (tn, fpn) = analyze(flat());
+        assert!(tn.0 < 1e-3);
+        assert!(fpn.fpn < 1e-3 && fpn.row < 1e-3 && fpn.col < 1e-3, "{fpn:?}");
+    }
+
+    #[test]
+    fn temporal_noise_is_recovered() {
+        let (tn, fpn) = analyze(SyntheticParams {
+            temporal_sigma: 3.0,
+            ..flat()
+        });
+        assert_close(tn.0, 3.0, 0.03);
+        // only the residual temporal noise of the mean over all frames remains
+        assert!(fpn.fpn < 0.5, "{fpn:?}");
+    }
+
+    #[test]
+    fn fixed_pattern_noise_is_recovered() {
+        let (tn, fpn) = analyze(SyntheticParams {
+            fpn_sigma: 4.0,
+            temporal_sigma: 1.0,
+            ..flat()
+        });
+        assert_close(fpn.fpn, 4.0, 0.03);
+        assert_close(tn.0, 1.0, 0.1);
+        assert!(fpn.row < 1.0 && fpn.col < 1.0, "{fpn:?}");
+    }
+
+    #[test]
+    fn row_banding_is_recovered() {
+        let (_, fpn) = analyze(SyntheticParams {
+            row_banding: 3.0,
+            temporal_sigma: 2.0,
+            ..flat()
+        });
+        assert_close(fpn.row, 3.0, 0.03);
+        assert!(fpn.col < 0.5, "{fpn:?}");
+    }
+
+    #[test]
+    fn column_banding_is_recovered() {
+        let (_, fpn) = analyze(SyntheticParams {
+            column_banding: 3.0,
+            temporal_sigma: 2.0,
+            ..flat()
+        });
+        assert_close(fpn.col, 3.0, 0.03);
+        assert!(fpn.row < 0.5, "{fpn:?}");
+    }
+
+    #[test]
+    fn hot_pixels_increase_fixed_pattern_noise() {
+        let params = SyntheticParams {
+            level: 64.0,
+            hot_pixels: 41,
+            ..flat()
+        };
+        let (tn, fpn) = analyze(params);
+        // binary mixture of hot pixels at full scale and pixels at the flat level
+        let p = params.hot_pixels as f32 / (params.width * params.height) as f32;
+        let expected = (255.0 - params.level) * (p * (1.0 - p)).sqrt();
+        assert_close(fpn.fpn, expected, 0.01);
+        assert!(tn.0 < 1e-3);
+    }
+
+    #[test]
+    fn hot_pixels_are_defect_pixels() {
+        let params = SyntheticParams {
+            level: 64.0,
+            hot_pixels: 41,
+            fpn_sigma: 4.0,
+            temporal_sigma: 3.0,
+            ..flat()
+        };
+        let maps = Noise::calc_noise_maps(&window_stack(params));
+        assert_eq!(Noise::calc_defect_pixels(&maps), params.hot_pixels as usize);
+    }
+
+    #[test]
+    fn noise_without_hot_pixels_has_no_defect_pixels() {
+        let maps = Noise::calc_noise_maps(&window_stack(SyntheticParams {
+            fpn_sigma: 4.0,
+            row_banding: 3.0,
+            temporal_sigma: 3.0,
+            ..flat()
+        }));
+        assert_eq!(Noise::calc_defect_pixels(&maps), 0);
+    }
+
+    #[test]
+    fn flicker_is_temporal_but_not_fixed_pattern_noise() {
+        let params = SyntheticParams {
+            flicker: 2.0,
+            temporal_sigma: 1.0,
+            ..flat()
+        };
+        let (tn, fpn) = analyze(params);
+        // the flicker moves all pixels of a row together, so it shows up fully in the row means
+        assert_close(fpn.row, params.flicker, 0.03);
+        assert_close(
+            tn.0,
+            (params.flicker.powi(2) + params.temporal_sigma.powi(2)).sqrt(),
+            0.03,
+        );
+        assert!(fpn.fpn < 0.5, "{fpn:?}");
+    }
+
+    // compares the previous frame path, which copied every frame twice and converted it completely, with reading the
+    // noise window from the frame buffer, run with `cargo test --release bench_frame_path -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_frame_path_1080p60() {
+        const WIDTH: usize = 1920;
+        const HEIGHT: usize = 1080;
+        const FPS: f64 = 60.0;
+        const N: u32 = 60;
+        let layout = FrameLayout {
+            width: WIDTH,
+            height: HEIGHT,
+            stride: WIDTH * 3,
+        };
+        let data: Vec<u8> = (0..WIDTH * HEIGHT * 3).map(|i| (i % 251) as u8).collect();
+        let report = |name: &str, per_frame: std::time::Duration| {
+            println!(
+                "{name}: {:.3} ms per frame, {:.1} % of one core at 1080p60",
+                per_frame.as_secs_f64() * 1e3,
+                per_frame.as_secs_f64() * FPS * 100.0
+            );
+        };
+
+        for (w, h) in [(100, 100), (WIDTH, HEIGHT)] {
+            let config = NoiseConfig {
+                x: (WIDTH - w) / 2,
+                y: (HEIGHT - h) / 2,
+                w,
+                h,
+                n_frames: 1,
+            };
+            let (x_end, y_end) = (config.x + w, config.y + h);
+
+            let start = Instant::now();
+            for _ in 0..N {
+                let pixbuf = SharedPixelBuffer::<Rgb8Pixel>::clone_from_slice(&data, WIDTH as u32, HEIGHT as u32);
+                let arr = Array3::<u8>::from_shape_vec((HEIGHT, WIDTH, 3), pixbuf.as_bytes().to_vec())
+                    .unwrap()
+                    .mapv(|v| v as f32);
+                let window = 0.299 * &arr.slice(s![config.y..y_end, config.x..x_end, 0])
+                    + 0.587 * &arr.slice(s![config.y..y_end, config.x..x_end, 1])
+                    + 0.114 * &arr.slice(s![config.y..y_end, config.x..x_end, 2]);
+                black_box(window);
+            }
+            let copying = start.elapsed() / N;
+
+            let mut window = Array2::<f32>::zeros((h, w));
+            let start = Instant::now();
+            for _ in 0..N {
+                Noise::read_window(black_box(&data), layout, &config, window.view_mut()).unwrap();
+                black_box(&window);
+            }
+            let zero_copy = start.elapsed() / N;
+
+            report(&format!("{w}x{h} window, copying frames"), copying);
+            report(&format!("{w}x{h} window, reading the buffer"), zero_copy);
+            assert!(zero_copy < copying);
+        }
+    }
+
+    // compares the metric calculation on one worker thread with one thread per core for several ROI sizes, run with
+    // `cargo test --release bench_noise_metrics -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_noise_metrics() {
+        const N_STACK: usize = 16;
+        const N: u32 = 5;
+        let single = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let all_cores = ThreadPoolBuilder::new().build().unwrap();
+
+        for (w, h) in [(64, 64), (256, 256), (1024, 1024), (1920, 1080)] {
+            let win_stack =
+                NoiseWindowStack::from_shape_fn((h, w, N_STACK), |(y, x, i)| ((x * 7 + y * 13 + i * 31) % 251) as f32);
+            let calc = || {
+                let maps = Noise::calc_noise_maps(&win_stack);
+                let fpn = Noise::calc_fixed_pattern_noise(&win_stack, &maps);
+                (Noise::calc_temporal_noise(&maps).0, fpn.fpn, fpn.row, fpn.col)
+            };
+            let time = |pool: &ThreadPool| {
+                let start = Instant::now();
+                for _ in 0..N {
+                    black_box(pool.install(calc));
+                }
+                start.elapsed() / N
+            };
+
+            let serial = time(&single);
+            let parallel = time(&all_cores);
+            println!(
+                "{w}x{h}x{N_STACK} stack: {:.3} ms on 1 thread, {:.3} ms on {} threads",
+                serial.as_secs_f64() * 1e3,
+                parallel.as_secs_f64() * 1e3,
+                all_cores.current_num_threads()
+            );
+            assert_eq!(single.install(calc), all_cores.install(calc));
+        }
+    }
+}
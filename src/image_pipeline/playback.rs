@@ -0,0 +1,117 @@
+//! Playback control of file sources: seeking, looping and restricting playback to a frame range.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use gst::{prelude::*, ClockTime, SeekFlags, SeekType};
+
+use crate::image_pipeline::{
+    bus::BusWatcher,
+    gstreamer::{GstError, ImagePipeline},
+    source::frame_time,
+};
+
+/// Frames of a file that are played back, end is inclusive and None plays to the end of the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct FrameRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+pub(crate) struct Playback {
+    pipeline: gst::Pipeline,
+    looping: AtomicBool,
+    range: Mutex<FrameRange>,
+}
+
+impl Playback {
+    /// Creates the playback control and starts restarting the playback at the end of stream if looping is enabled.
+    pub(crate) fn new(pipeline: gst::Pipeline, bus: &BusWatcher) -> Arc<Self> {
+        let playback = Arc::new(Playback {
+            pipeline,
+            looping: AtomicBool::new(true),
+            range: Mutex::new(FrameRange::default()),
+        });
+
+        let eos_playback = playback.clone();
+        bus.add_handler(move |msg| {
+            if let gst::MessageView::Eos(_) = msg.view() {
+                eos_playback.handle_eos();
+            }
+        });
+
+        playback
+    }
+
+    pub(crate) fn set_looping(&self, looping: bool) {
+        self.looping.store(looping, Ordering::Relaxed);
+    }
+
+    /// Restricts playback to the given frame range and seeks to its first frame.
+    pub(crate) fn set_range(&self, range: FrameRange) -> Result<(), GstError> {
+        if range.end.is_some_and(|end| end < range.start) {
+            return Err(GstError::InvalidValue);
+        }
+        *self.range.lock().expect("Range Mutex poisened") = range;
+        self.seek_to_frame(range.start)
+    }
+
+    /// Resets the frame range to the complete file.
+    pub(crate) fn reset_range(&self) {
+        *self.range.lock().expect("Range Mutex poisened") = FrameRange::default();
+    }
+
+    /// Seeks to the given position in seconds, staying within the frame range.
+    pub(crate) fn seek(&self, position: f64) -> Result<(), GstError> {
+        let range = *self.range.lock().expect("Range Mutex poisened");
+        let start = self.frame_time(range.start).unwrap_or(ClockTime::ZERO);
+        let position = ClockTime::from_nseconds((position.max(0.0) * 1e9) as u64).max(start);
+        self.seek_segment(position, range)
+    }
+
+    /// Returns the current position and the duration of the file in seconds.
+    pub(crate) fn position(&self) -> Option<(f64, f64)> {
+        let position = self.pipeline.query_position::<ClockTime>()?;
+        let duration = self.pipeline.query_duration::<ClockTime>()?;
+        Some((position.seconds_f64(), duration.seconds_f64()))
+    }
+
+    fn handle_eos(&self) {
+        if !self.looping.load(Ordering::Relaxed) {
+            println!("End of file reached.");
+            return;
+        }
+        let start = self.range.lock().expect("Range Mutex poisened").start;
+        if let Err(e) = self.seek_to_frame(start) {
+            eprintln!("Could not restart playback: {e}");
+        }
+    }
+
+    fn seek_to_frame(&self, frame: u64) -> Result<(), GstError> {
+        let range = *self.range.lock().expect("Range Mutex poisened");
+        let position = self.frame_time(frame).ok_or(GstError::InvalidValue)?;
+        self.seek_segment(position, range)
+    }
+
+    // flushing seek that stops playback after the last frame of the range
+    fn seek_segment(&self, position: ClockTime, range: FrameRange) -> Result<(), GstError> {
+        let stop = range.end.and_then(|end| self.frame_time(end + 1));
+        let stop_type = if stop.is_some() { SeekType::Set } else { SeekType::None };
+        self.pipeline.seek(
+            1.0,
+            SeekFlags::FLUSH | SeekFlags::ACCURATE,
+            SeekType::Set,
+            Some(position),
+            stop_type,
+            stop,
+        )?;
+        Ok(())
+    }
+
+    /// Returns the presentation time of the given frame according to the framerate of the stream.
+    fn frame_time(&self, frame: u64) -> Option<ClockTime> {
+        frame_time(frame, ImagePipeline::get_stream_info(&self.pipeline)?.framerate)
+    }
+}
@@ -0,0 +1,233 @@
+//! Recording of the unconverted source frames for a later replay as image sequence.
+//!
+//! A recording is a directory with all frames concatenated into a raw dump and a JSON sidecar that describes the
+//! frame format, the caps and the v4l2 controls of the recorded device.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::prelude::*;
+use gst::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::image_pipeline::{
+    device_caps::RawSourceCaps,
+    image_sequence::RawPixelFormat,
+    source::{frame_planes, VideoSource},
+    v4l2_controls,
+};
+
+const RECORDING_INFO_FILE: &str = "recording.json";
+const RECORDING_FRAMES_FILE: &str = "frames.raw";
+
+#[derive(Debug, Error)]
+pub enum RecordingError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("No video stream to record, start the video first.")]
+    NoStream,
+
+    #[error("Pixel format {0} can not be recorded.")]
+    UnsupportedFormat(String),
+}
+
+/// Sidecar of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RecordingInfo {
+    pub started: String,
+    pub source: String,
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: String,
+    pub framerate_numer: i32,
+    pub framerate_denom: i32,
+    pub caps: String,
+    pub frame_count: u64,
+    pub controls: BTreeMap<String, i64>,
+}
+
+impl RecordingInfo {
+    /// Reads the sidecar of the given directory, returns None if the directory is not a recording.
+    pub(crate) fn read(dir: &Path) -> Result<Option<Self>, RecordingError> {
+        let path = dir.join(RECORDING_INFO_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    fn write(&self, dir: &Path) -> Result<(), RecordingError> {
+        fs::write(dir.join(RECORDING_INFO_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+struct FrameWriter {
+    file: BufWriter<File>,
+    info: gst_video::VideoInfo,
+    caps: gst::Caps,
+    frame_count: u64,
+    caps_changed: bool,
+}
+
+impl FrameWriter {
+    /// Appends the frame without row padding, frames with different caps than the recording are skipped.
+    fn write(&mut self, pad: &gst::Pad, buffer: &gst::BufferRef) -> io::Result<()> {
+        if pad.current_caps().as_ref() != Some(&self.caps) {
+            if !self.caps_changed {
+                eprintln!("Video format changed, frames are not recorded until it is restored.");
+                self.caps_changed = true;
+            }
+            return Ok(());
+        }
+        self.caps_changed = false;
+
+        // the strides of the buffer may differ from the defaults of the caps, e.g. for frames of a decoder
+        let Ok(frame) = gst_video::VideoFrameRef::from_buffer_ref_readable(buffer, &self.info) else {
+            eprintln!("Failed to map buffer readable.");
+            return Ok(());
+        };
+        for (plane, (row_bytes, rows)) in frame_planes(&self.info).into_iter().enumerate() {
+            let Ok(data) = frame.plane_data(plane as u32) else {
+                eprintln!("Failed to read plane {plane} of the frame.");
+                return Ok(());
+            };
+            let stride = frame.plane_stride()[plane] as usize;
+            for row in 0..rows {
+                let start = row * stride;
+                self.file.write_all(&data[start..start + row_bytes])?;
+            }
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+}
+
+pub(crate) struct Recording {
+    dir: PathBuf,
+    pad: gst::Pad,
+    probe: Option<gst::PadProbeId>,
+    writer: Arc<Mutex<FrameWriter>>,
+    info: RecordingInfo,
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if let Some(probe) = self.probe.take() {
+            self.pad.remove_probe(probe);
+        }
+    }
+}
+
+impl Recording {
+    /// Starts recording the frames entering the converter into a new directory in the output directory.
+    pub(crate) fn start(
+        pipeline: &gst::Pipeline,
+        output_dir: &Path,
+        source: &VideoSource,
+        caps: &RawSourceCaps,
+    ) -> Result<Self, RecordingError> {
+        let pad = pipeline
+            .by_name("convert")
+            .and_then(|convert| convert.static_pad("sink"))
+            .ok_or(RecordingError::NoStream)?;
+        let stream_caps = pad.current_caps().ok_or(RecordingError::NoStream)?;
+        let video_info = gst_video::VideoInfo::from_caps(&stream_caps).map_err(|_| RecordingError::NoStream)?;
+
+        // only formats that can be replayed as image sequence are recorded
+        let pixel_format = video_info.format().to_str().to_string();
+        if RawPixelFormat::try_from(pixel_format.as_str()).is_err() {
+            return Err(RecordingError::UnsupportedFormat(pixel_format));
+        }
+
+        let controls = match source
+            .v4l2_device_path(caps)
+            .map(|p| v4l2_controls::read_all_controls(&p))
+        {
+            Some(Ok(controls)) => controls.into_iter().collect(),
+            Some(Err(e)) => {
+                eprintln!("Could not read v4l2 controls: {e}");
+                BTreeMap::new()
+            }
+            None => BTreeMap::new(),
+        };
+
+        let now = Local::now();
+        let mut dir_name = now.format("%Y-%m-%d_%H:%M:%S%.3f").to_string();
+        dir_name.push_str("_recording");
+        let dir = output_dir.join(dir_name);
+        fs::create_dir_all(&dir)?;
+
+        let info = RecordingInfo {
+            started: now.to_rfc3339(),
+            source: source.name(caps),
+            width: video_info.width(),
+            height: video_info.height(),
+            pixel_format,
+            framerate_numer: video_info.fps().numer(),
+            framerate_denom: video_info.fps().denom(),
+            caps: stream_caps.to_string(),
+            frame_count: 0,
+            controls,
+        };
+        // the sidecar is written right away so that an interrupted recording can still be replayed
+        info.write(&dir)?;
+
+        let writer = Arc::new(Mutex::new(FrameWriter {
+            file: BufWriter::new(File::create(dir.join(RECORDING_FRAMES_FILE))?),
+            info: video_info,
+            caps: stream_caps,
+            frame_count: 0,
+            caps_changed: false,
+        }));
+
+        let probe_writer = writer.clone();
+        let probe = pad.add_probe(gst::PadProbeType::BUFFER, move |pad, probe_info| {
+            if let Some(buffer) = probe_info.buffer() {
+                if let Err(e) = probe_writer
+                    .lock()
+                    .expect("Recording Mutex poisened")
+                    .write(pad, buffer)
+                {
+                    eprintln!("Writing recorded frame failed: {e}");
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+
+        println!("Started recording to {dir:?}.");
+        Ok(Recording {
+            dir,
+            pad,
+            probe,
+            writer,
+            info,
+        })
+    }
+
+    /// Stops the recording, completes the sidecar and returns the recording directory.
+    pub(crate) fn stop(mut self) -> Result<PathBuf, RecordingError> {
+        if let Some(probe) = self.probe.take() {
+            self.pad.remove_probe(probe);
+        }
+
+        let mut writer = self.writer.lock().expect("Recording Mutex poisened");
+        writer.file.flush()?;
+        self.info.frame_count = writer.frame_count;
+        drop(writer);
+        self.info.write(&self.dir)?;
+
+        println!("Recorded {} frames to {:?}.", self.info.frame_count, self.dir);
+        Ok(self.dir.clone())
+    }
+}
@@ -0,0 +1,283 @@
+//! Self-contained HTML measurement report with all images embedded as data URIs.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::prelude::*;
+use image::{imageops::FilterType, ImageFormat, Rgb, RgbImage};
+use ndarray::prelude::*;
+
+use crate::image_pipeline::{
+    export::{ExportError, MeasurementReport, Roi},
+    noise::NoiseMaps,
+};
+
+// small noise windows are upscaled so that single pixels are visible in the heat map
+const MIN_HEAT_MAP_WIDTH: u32 = 256;
+const ROI_COLOR: Rgb<u8> = Rgb([255, 0, 0]);
+const PLOT_WIDTH: f32 = 600.0;
+const PLOT_HEIGHT: f32 = 200.0;
+const PLOT_MARGIN: f32 = 40.0;
+
+/// Writes an HTML report of the given measurement into the output directory and returns its path.
+///
+/// The snapshot is the frame the measurement ended with, the report has no frame if it could not be copied.
+pub(crate) fn write_html_report(
+    output_dir: &Path,
+    report: &MeasurementReport,
+    maps: &NoiseMaps,
+    snapshot: Option<RgbImage>,
+) -> Result<PathBuf, ExportError> {
+    fs::create_dir_all(output_dir)?;
+
+    let screenshot = match snapshot {
+        Some(mut img) => {
+            draw_roi(&mut img, &report.roi);
+            Some(png_data_uri(&img)?)
+        }
+        None => None,
+    };
+    let (heat_map, std_max) = heat_map(&maps.temporal_std);
+    let heat_map = png_data_uri(&heat_map)?;
+    let row_profile = svg_data_uri(&profile_plot(&maps.row_profile, "row", "#2ca02c"));
+    let col_profile = svg_data_uri(&profile_plot(&maps.col_profile, "column", "#ff7f0e"));
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Noise measurement report</title>\n\
+<style>\nbody {{ font-family: sans-serif; color: #24266b; margin: 2em; }}\n\
+table {{ border-collapse: collapse; margin-bottom: 1em; }}\n\
+td, th {{ border: 1px solid #24266b; padding: 4px 10px; text-align: left; }}\n\
+img {{ max-width: 100%; border: 1px solid #24266b; image-rendering: pixelated; }}\n</style>\n</head>\n<body>\n\
+<h1>Noise measurement report</h1>\n<p>Measured at {}</p>\n",
+        escape(&report.timestamp)
+    );
+
+    html.push_str("<h2>Metrics</h2>\n<table>\n");
+    let noise = |value: f32| format!("{value} {}", report.units);
+    table_row(&mut html, "Temporal noise", &noise(report.metrics.temporal_noise));
+    table_row(
+        &mut html,
+        "Fixed pattern noise",
+        &noise(report.metrics.fixed_pattern_noise),
+    );
+    table_row(&mut html, "Row noise", &noise(report.metrics.row_noise));
+    table_row(&mut html, "Column noise", &noise(report.metrics.column_noise));
+    if let Some(snr) = report.metrics.snr {
+        table_row(&mut html, "SNR", &snr.to_string());
+    }
+    if let Some(defect_pixels) = report.metrics.defect_pixels {
+        table_row(&mut html, "Defect pixels", &defect_pixels.to_string());
+    }
+    if let Some(verdict) = &report.verdict {
+        table_row(&mut html, "Verdict", verdict.label());
+        table_row(
+            &mut html,
+            "Limits",
+            &format!("{} (noise in {})", verdict.limits.to_compact_string(), report.units),
+        );
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Device</h2>\n<table>\n");
+    table_row(&mut html, "Device path", &report.device_path);
+    table_row(&mut html, "Resolution", &format!("{}x{}", report.width, report.height));
+    table_row(&mut html, "Framerate", &report.framerate);
+    table_row(
+        &mut html,
+        "Pixel format",
+        report.pixel_format.as_deref().unwrap_or("unknown"),
+    );
+    table_row(
+        &mut html,
+        "Noise window",
+        &format!(
+            "x: {}, y: {}, width: {}, height: {}",
+            report.roi.x, report.roi.y, report.roi.w, report.roi.h
+        ),
+    );
+    table_row(&mut html, "Frames", &report.frame_count.to_string());
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Frame timing</h2>\n<table>\n");
+    table_row(
+        &mut html,
+        "Effective framerate",
+        &format!("{:.3} fps", report.timing.effective_fps),
+    );
+    table_row(
+        &mut html,
+        "Frame interval",
+        &format!(
+            "{:.3} ms ± {:.3} ms ({:.3} - {:.3} ms)",
+            report.timing.interval_mean_ms,
+            report.timing.interval_jitter_ms,
+            report.timing.interval_min_ms,
+            report.timing.interval_max_ms
+        ),
+    );
+    table_row(
+        &mut html,
+        "Interval histogram (quarters of the frame interval)",
+        &report.timing.histogram_string(),
+    );
+    table_row(&mut html, "Dropped frames", &report.timing.dropped_frames.to_string());
+    table_row(
+        &mut html,
+        "Duplicated frames",
+        &report.timing.duplicated_frames.to_string(),
+    );
+    let skipped = match (report.timing.skipped_frames, report.timing.block_timeouts) {
+        (0, _) => "0".to_string(),
+        (n, 0) => format!("{n}, the noise calculation could not keep up with the source"),
+        (n, timeouts) => format!("{n}, {timeouts} of them dropped by the block policy after its timeout"),
+    };
+    table_row(&mut html, "Skipped frames", &skipped);
+    html.push_str("</table>\n");
+
+    if let Some(screenshot) = screenshot {
+        let _ = write!(
+            html,
+            "<h2>Last frame of the measurement</h2>\n<img src=\"{screenshot}\" alt=\"Frame with noise window\">\n"
+        );
+    }
+
+    let _ = write!(
+        html,
+        "<h2>Temporal noise heat map</h2>\n<img src=\"{heat_map}\" alt=\"Temporal noise heat map\">\n\
+<p>Per-pixel temporal standard deviation from 0 (blue) to {std_max:.3} (red).</p>\n\
+<h2>Row profile</h2>\n<img src=\"{row_profile}\" alt=\"Row profile\">\n\
+<h2>Column profile</h2>\n<img src=\"{col_profile}\" alt=\"Column profile\">\n"
+    );
+
+    if !report.controls.is_empty() {
+        html.push_str("<h2>v4l2 controls</h2>\n<table>\n");
+        for (name, value) in &report.controls {
+            table_row(&mut html, name, &value.to_string());
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+
+    let mut file_name = Local::now().format("%Y-%m-%d_%H:%M:%S%.3f").to_string();
+    file_name.push_str("_noise_report.html");
+    let path = output_dir.join(file_name);
+    fs::write(&path, html)?;
+
+    Ok(path)
+}
+
+fn table_row(html: &mut String, name: &str, value: &str) {
+    let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", escape(name), escape(value));
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn png_data_uri(img: &RgbImage) -> Result<String, ExportError> {
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(format!("data:image/png;base64,{}", STANDARD.encode(png)))
+}
+
+fn svg_data_uri(svg: &str) -> String {
+    format!("data:image/svg+xml;base64,{}", STANDARD.encode(svg))
+}
+
+/// Draws the noise window as a red frame into the image.
+fn draw_roi(img: &mut RgbImage, roi: &Roi) {
+    let (width, height) = img.dimensions();
+    if roi.w == 0 || roi.h == 0 || roi.x as u32 >= width || roi.y as u32 >= height {
+        return;
+    }
+    let x1 = roi.x as u32;
+    let y1 = roi.y as u32;
+    let x2 = ((roi.x + roi.w) as u32).min(width) - 1;
+    let y2 = ((roi.y + roi.h) as u32).min(height) - 1;
+
+    // 2 pixel wide frame, drawn inside the noise window
+    for offset in 0..2 {
+        for x in x1..=x2 {
+            img.put_pixel(x, (y1 + offset).min(y2), ROI_COLOR);
+            img.put_pixel(x, y2.saturating_sub(offset).max(y1), ROI_COLOR);
+        }
+        for y in y1..=y2 {
+            img.put_pixel((x1 + offset).min(x2), y, ROI_COLOR);
+            img.put_pixel(x2.saturating_sub(offset).max(x1), y, ROI_COLOR);
+        }
+    }
+}
+
+/// Renders the map with a blue-to-red color map and returns it together with the value mapped to red.
+fn heat_map(map: &Array2<f32>) -> (RgbImage, f32) {
+    let max = map.fold(0.0_f32, |acc, v| acc.max(*v));
+    let scale = if max > 0.0 { 1.0 / max } else { 0.0 };
+    let (height, width) = map.dim();
+
+    let img = RgbImage::from_fn(width as u32, height as u32, |x, y| {
+        let t = map[[y as usize, x as usize]] * scale;
+        // jet color map
+        let channel = |center: f32| ((1.5 - (4.0 * t - center).abs()).clamp(0.0, 1.0) * 255.0) as u8;
+        Rgb([channel(3.0), channel(2.0), channel(1.0)])
+    });
+
+    if width == 0 || width as u32 >= MIN_HEAT_MAP_WIDTH {
+        return (img, max);
+    }
+    let factor = MIN_HEAT_MAP_WIDTH.div_ceil(width as u32);
+    let img = image::imageops::resize(&img, width as u32 * factor, height as u32 * factor, FilterType::Nearest);
+    (img, max)
+}
+
+/// Renders a line plot of the mean value per row or column as SVG.
+fn profile_plot(profile: &Array1<f32>, axis_name: &str, color: &str) -> String {
+    let min = profile.fold(f32::INFINITY, |acc, v| acc.min(*v));
+    let max = profile.fold(f32::NEG_INFINITY, |acc, v| acc.max(*v));
+    let range = if max > min { max - min } else { 1.0 };
+    let plot_w = PLOT_WIDTH - 2.0 * PLOT_MARGIN;
+    let plot_h = PLOT_HEIGHT - 2.0 * PLOT_MARGIN;
+    let x_step = if profile.len() > 1 {
+        plot_w / (profile.len() - 1) as f32
+    } else {
+        0.0
+    };
+
+    let points = profile
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let x = PLOT_MARGIN + i as f32 * x_step;
+            let y = PLOT_MARGIN + plot_h - (v - min) / range * plot_h;
+            format!("{x:.1},{y:.1}")
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{PLOT_WIDTH}\" height=\"{PLOT_HEIGHT}\" \
+font-family=\"sans-serif\" font-size=\"11\">\
+<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\
+<rect x=\"{PLOT_MARGIN}\" y=\"{PLOT_MARGIN}\" width=\"{plot_w}\" height=\"{plot_h}\" fill=\"none\" stroke=\"#24266b\"/>\
+<polyline points=\"{points}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"1.5\"/>\
+<text x=\"{label_x}\" y=\"{top}\" text-anchor=\"end\">{max:.2}</text>\
+<text x=\"{label_x}\" y=\"{bottom}\" text-anchor=\"end\">{min:.2}</text>\
+<text x=\"{center}\" y=\"{axis_y}\" text-anchor=\"middle\">{axis_name} (0 to {last}), mean value</text>\
+</svg>",
+        label_x = PLOT_MARGIN - 4.0,
+        top = PLOT_MARGIN + 4.0,
+        bottom = PLOT_MARGIN + plot_h,
+        center = PLOT_WIDTH / 2.0,
+        axis_y = PLOT_HEIGHT - PLOT_MARGIN / 2.0,
+        last = profile.len().saturating_sub(1),
+    )
+}
@@ -0,0 +1,452 @@
+//! Named noise window presets, e.g. the ROIs and frame counts a test procedure specifies for a product.
+//!
+//! A preset is either given in pixels or relative to the frame, so that it adapts to the current resolution. The
+//! presets are stored next to the settings and can be imported from and exported to a JSON file.
+
+use std::{
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+use slint::{Model, ModelRc, SharedString, VecModel};
+
+use crate::image_pipeline::config::{ConfigError, Roi, Settings};
+use crate::App;
+
+// first entry of the preset combo box, the noise window is not bound to a preset
+const NO_PRESET: &str = "custom";
+
+/// Noise window of a preset.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum PresetRoi {
+    /// pixels of the frame
+    Absolute { x: i32, y: i32, w: i32, h: i32 },
+    /// percent of the frame width and height
+    Relative { x: f32, y: f32, w: f32, h: f32 },
+    /// centered in the frame, size in percent of the frame width and height
+    Centered { w: f32, h: f32 },
+}
+
+/// The modes a preset can be saved in, same order as the mode combo box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PresetMode {
+    Absolute,
+    Relative,
+    Centered,
+}
+
+impl PresetMode {
+    const ALL: [PresetMode; 3] = [PresetMode::Absolute, PresetMode::Relative, PresetMode::Centered];
+}
+
+impl Display for PresetMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetMode::Absolute => write!(f, "pixels"),
+            PresetMode::Relative => write!(f, "percent of frame"),
+            PresetMode::Centered => write!(f, "centered, percent"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RoiPreset {
+    pub name: String,
+    pub roi: PresetRoi,
+    /// frames of a noise window stack, the frame count is not changed if it is not set
+    #[serde(default)]
+    pub number_of_frames: Option<i32>,
+}
+
+impl PresetRoi {
+    /// Describes the noise window in the given mode, relative modes need the frame size.
+    fn from_roi(mode: PresetMode, roi: Roi, width: i32, height: i32) -> Option<Self> {
+        let percent = |value: i32, size: i32| value as f32 / size as f32 * 100.0;
+        match mode {
+            PresetMode::Absolute => Some(PresetRoi::Absolute {
+                x: roi.x,
+                y: roi.y,
+                w: roi.w,
+                h: roi.h,
+            }),
+            _ if width <= 0 || height <= 0 => None,
+            PresetMode::Relative => Some(PresetRoi::Relative {
+                x: percent(roi.x, width),
+                y: percent(roi.y, height),
+                w: percent(roi.w, width),
+                h: percent(roi.h, height),
+            }),
+            PresetMode::Centered => Some(PresetRoi::Centered {
+                w: percent(roi.w, width),
+                h: percent(roi.h, height),
+            }),
+        }
+    }
+
+    /// Returns the noise window in pixels of a frame of the given size, clamped to the frame.
+    ///
+    /// Absolute windows are not clamped if the frame size is unknown.
+    pub(crate) fn resolve(&self, width: i32, height: i32) -> Roi {
+        let pixels = |percent: f32, size: i32| (percent / 100.0 * size as f32).round() as i32;
+        let roi = match *self {
+            PresetRoi::Absolute { x, y, w, h } => Roi { x, y, w, h },
+            PresetRoi::Relative { x, y, w, h } => Roi {
+                x: pixels(x, width),
+                y: pixels(y, height),
+                w: pixels(w, width),
+                h: pixels(h, height),
+            },
+            PresetRoi::Centered { w, h } => {
+                let (w, h) = (pixels(w, width), pixels(h, height));
+                Roi {
+                    x: (width - w) / 2,
+                    y: (height - h) / 2,
+                    w,
+                    h,
+                }
+            }
+        };
+        roi.clamped(width, height)
+    }
+}
+
+/// Returns the presets available before any preset was saved.
+fn default_presets() -> Vec<RoiPreset> {
+    let preset = |name: &str, roi| RoiPreset {
+        name: name.to_string(),
+        roi,
+        number_of_frames: None,
+    };
+    vec![
+        preset(
+            "full frame",
+            PresetRoi::Relative {
+                x: 0.0,
+                y: 0.0,
+                w: 100.0,
+                h: 100.0,
+            },
+        ),
+        preset("center 10%", PresetRoi::Centered { w: 10.0, h: 10.0 }),
+        preset("center 50%", PresetRoi::Centered { w: 50.0, h: 50.0 }),
+        preset(
+            "top-left 64x64",
+            PresetRoi::Absolute {
+                x: 0,
+                y: 0,
+                w: 64,
+                h: 64,
+            },
+        ),
+    ]
+}
+
+/// Returns the path the presets are stored at, next to the settings.
+pub(crate) fn default_path() -> PathBuf {
+    Settings::default_path().with_file_name("roi_presets.json")
+}
+
+pub(crate) fn load(path: &Path) -> Result<Vec<RoiPreset>, ConfigError> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+pub(crate) fn save(presets: &[RoiPreset], path: &Path) -> Result<(), ConfigError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(presets)?)?;
+    Ok(())
+}
+
+/// Adds the presets, presets of the same name are replaced.
+fn merge(presets: &mut Vec<RoiPreset>, imported: Vec<RoiPreset>) {
+    for preset in imported {
+        match presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+    }
+}
+
+impl App {
+    /// Sets up the preset row, the preset of the given name is applied once the first frame is shown.
+    pub(crate) fn init_roi_presets(self: &Arc<App>, selected: Option<&str>) {
+        let path = default_path();
+        let presets = match load(&path) {
+            Ok(presets) => presets,
+            Err(ConfigError::Io(e)) if e.kind() == io::ErrorKind::NotFound => default_presets(),
+            Err(e) => {
+                eprintln!("Could not load ROI presets from {}: {e}", path.display());
+                default_presets()
+            }
+        };
+        let modes: Vec<SharedString> = PresetMode::ALL.iter().map(|m| m.to_string().into()).collect();
+        self.set_roi_preset_modes(ModelRc::new(VecModel::from(modes)));
+        self.show_roi_presets(&presets, selected);
+        let presets = Arc::new(Mutex::new(presets));
+
+        self.on_selected_roi_preset({
+            let ui = self.clone();
+            let presets = presets.clone();
+            move |idx| {
+                let presets = presets.lock().expect("Preset Mutex poisened");
+                if let Some(preset) = preset_at(&presets, idx) {
+                    ui.set_roi_preset_name(preset.name.as_str().into());
+                    ui.apply_roi_preset(preset, true);
+                }
+            }
+        });
+
+        // relative presets follow the resolution of the frames
+        self.on_frame_size_changed({
+            let ui = self.clone();
+            let presets = presets.clone();
+            move || {
+                let presets = presets.lock().expect("Preset Mutex poisened");
+                if let Some(preset) = preset_at(&presets, ui.get_roi_preset_index()) {
+                    ui.apply_roi_preset(preset, false);
+                }
+            }
+        });
+
+        self.on_save_roi_preset({
+            let ui = self.clone();
+            let presets = presets.clone();
+            move |name, mode_idx| {
+                let name = name.trim().to_string();
+                let mode = PresetMode::ALL.get(mode_idx.max(0) as usize).copied();
+                let status = match (name.is_empty(), mode) {
+                    (true, _) => "Enter a name for the preset.".to_string(),
+                    (false, None) => "Select how the preset is saved.".to_string(),
+                    (false, Some(mode)) => {
+                        let roi = Roi {
+                            x: ui.get_noise_x(),
+                            y: ui.get_noise_y(),
+                            w: ui.get_noise_w(),
+                            h: ui.get_noise_h(),
+                        };
+                        match PresetRoi::from_roi(mode, roi, ui.get_frame_width(), ui.get_frame_height()) {
+                            Some(preset_roi) => {
+                                let mut presets = presets.lock().expect("Preset Mutex poisened");
+                                let preset = RoiPreset {
+                                    name: name.clone(),
+                                    roi: preset_roi,
+                                    number_of_frames: Some(ui.get_number_of_frames()),
+                                };
+                                merge(&mut presets, vec![preset]);
+                                ui.show_roi_presets(&presets, Some(&name));
+                                ui.store_roi_presets(&presets, format!("Saved preset {name} ({mode})."))
+                            }
+                            None => "A relative preset needs a frame, start the video first.".to_string(),
+                        }
+                    }
+                };
+                ui.set_roi_preset_status(status.into());
+            }
+        });
+
+        self.on_delete_roi_preset({
+            let ui = self.clone();
+            let presets = presets.clone();
+            move |idx| {
+                let mut presets = presets.lock().expect("Preset Mutex poisened");
+                let Some(preset_idx) = usize::try_from(idx - 1).ok().filter(|&i| i < presets.len()) else {
+                    return;
+                };
+                let preset = presets.remove(preset_idx);
+                ui.show_roi_presets(&presets, None);
+                let status = ui.store_roi_presets(&presets, format!("Deleted preset {}.", preset.name));
+                ui.set_roi_preset_status(status.into());
+            }
+        });
+
+        self.on_import_roi_presets({
+            let ui = self.clone();
+            let presets = presets.clone();
+            move || {
+                let Some(file) = FileDialog::new().add_filter("JSON", &["json"]).pick_file() else {
+                    return;
+                };
+                let status = match load(&file) {
+                    Ok(imported) => {
+                        let count = imported.len();
+                        let mut presets = presets.lock().expect("Preset Mutex poisened");
+                        merge(&mut presets, imported);
+                        ui.show_roi_presets(&presets, None);
+                        ui.store_roi_presets(&presets, format!("Imported {count} presets from {}.", file.display()))
+                    }
+                    Err(e) => format!("Could not import presets from {}: {e}", file.display()),
+                };
+                ui.set_roi_preset_status(status.into());
+            }
+        });
+
+        self.on_export_roi_presets({
+            let ui = self.clone();
+            move || {
+                let Some(file) = FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name("roi_presets.json")
+                    .save_file()
+                else {
+                    return;
+                };
+                let presets = presets.lock().expect("Preset Mutex poisened");
+                let status = match save(&presets, &file) {
+                    Ok(()) => format!("Exported {} presets to {}.", presets.len(), file.display()),
+                    Err(e) => format!("Could not export presets to {}: {e}", file.display()),
+                };
+                ui.set_roi_preset_status(status.into());
+            }
+        });
+    }
+
+    /// Returns the name of the selected preset, None if the noise window is not bound to a preset.
+    pub(crate) fn selected_roi_preset(&self) -> Option<String> {
+        let idx = usize::try_from(self.get_roi_preset_index())
+            .ok()
+            .filter(|&idx| idx > 0)?;
+        self.get_roi_presets().row_data(idx).map(|name| name.to_string())
+    }
+
+    // the resolved window becomes the default window, so that it is kept when playback starts or the source changes
+    fn apply_roi_preset(&self, preset: &RoiPreset, set_frames: bool) {
+        let roi = preset.roi.resolve(self.get_frame_width(), self.get_frame_height());
+        self.set_default_noise_x(roi.x);
+        self.set_default_noise_y(roi.y);
+        self.set_default_noise_w(roi.w);
+        self.set_default_noise_h(roi.h);
+        self.invoke_reset_noise_window();
+        if let Some(n_frames) = preset.number_of_frames.filter(|_| set_frames) {
+            self.set_number_of_frames(n_frames.max(1));
+        }
+    }
+
+    fn show_roi_presets(&self, presets: &[RoiPreset], selected: Option<&str>) {
+        let names: Vec<SharedString> = std::iter::once(NO_PRESET.into())
+            .chain(presets.iter().map(|preset| preset.name.as_str().into()))
+            .collect();
+        let idx = selected
+            .and_then(|name| presets.iter().position(|preset| preset.name == name))
+            .map_or(0, |idx| idx as i32 + 1);
+        self.set_roi_presets(ModelRc::new(VecModel::from(names)));
+        self.set_roi_preset_index(idx);
+        if let Some(name) = selected.filter(|_| idx > 0) {
+            self.set_roi_preset_name(name.into());
+        }
+    }
+
+    // returns the status line of the change
+    fn store_roi_presets(&self, presets: &[RoiPreset], status: String) -> String {
+        let path = default_path();
+        match save(presets, &path) {
+            Ok(()) => status,
+            Err(e) => format!("{status} Could not store the presets in {}: {e}", path.display()),
+        }
+    }
+}
+
+// the first entry of the combo box is not a preset
+fn preset_at(presets: &[RoiPreset], idx: i32) -> Option<&RoiPreset> {
+    usize::try_from(idx - 1).ok().and_then(|idx| presets.get(idx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roi(x: i32, y: i32, w: i32, h: i32) -> Roi {
+        Roi { x, y, w, h }
+    }
+
+    #[test]
+    fn absolute_is_kept() {
+        let preset = PresetRoi::Absolute {
+            x: 10,
+            y: 20,
+            w: 64,
+            h: 32,
+        };
+        assert_eq!(preset.resolve(1920, 1080), roi(10, 20, 64, 32));
+        // the frame size is not known before the first frame
+        assert_eq!(preset.resolve(0, 0), roi(10, 20, 64, 32));
+    }
+
+    #[test]
+    fn relative_scales_with_the_frame() {
+        let preset = PresetRoi::Relative {
+            x: 10.0,
+            y: 20.0,
+            w: 50.0,
+            h: 25.0,
+        };
+        assert_eq!(preset.resolve(1920, 1080), roi(192, 216, 960, 270));
+        assert_eq!(preset.resolve(640, 480), roi(64, 96, 320, 120));
+    }
+
+    #[test]
+    fn centered_is_centered() {
+        let preset = PresetRoi::Centered { w: 10.0, h: 10.0 };
+        assert_eq!(preset.resolve(1920, 1080), roi(864, 486, 192, 108));
+    }
+
+    #[test]
+    fn centered_in_odd_frame_sizes() {
+        let preset = PresetRoi::Centered { w: 50.0, h: 50.0 };
+        assert_eq!(preset.resolve(641, 481), roi(160, 120, 321, 241));
+
+        // the margins differ by at most one pixel if the window can not be centered exactly
+        for (width, height) in [(1001, 1001), (639, 479), (3, 5)] {
+            for percent in [10.0, 33.3, 50.0, 99.0] {
+                let window = PresetRoi::Centered { w: percent, h: percent }.resolve(width, height);
+                let right = width - window.x - window.w;
+                let bottom = height - window.y - window.h;
+                assert!(window.w >= 1 && window.h >= 1, "{window:?}");
+                assert!(right >= 0 && bottom >= 0, "{window:?} outside {width}x{height}");
+                assert!((right - window.x).abs() <= 1, "{window:?} in {width}x{height}");
+                assert!((bottom - window.y).abs() <= 1, "{window:?} in {width}x{height}");
+            }
+        }
+    }
+
+    #[test]
+    fn presets_larger_than_the_frame_are_clamped() {
+        let absolute = PresetRoi::Absolute {
+            x: 1800,
+            y: 1000,
+            w: 400,
+            h: 400,
+        };
+        assert_eq!(absolute.resolve(1920, 1080), roi(1800, 1000, 120, 80));
+        assert_eq!(absolute.resolve(640, 480), roi(639, 479, 1, 1));
+
+        let relative = PresetRoi::Relative {
+            x: 50.0,
+            y: 50.0,
+            w: 100.0,
+            h: 100.0,
+        };
+        assert_eq!(relative.resolve(1920, 1080), roi(960, 540, 960, 540));
+
+        let centered = PresetRoi::Centered { w: 150.0, h: 200.0 };
+        assert_eq!(centered.resolve(1920, 1080), roi(0, 0, 1920, 1080));
+    }
+
+    #[test]
+    fn saved_window_is_restored() {
+        let window = roi(100, 50, 200, 100);
+        for mode in [PresetMode::Absolute, PresetMode::Relative] {
+            let preset = PresetRoi::from_roi(mode, window, 800, 600).unwrap();
+            assert_eq!(preset.resolve(800, 600), window, "{mode}");
+        }
+        // a centered preset keeps the size only
+        let preset = PresetRoi::from_roi(PresetMode::Centered, window, 800, 600).unwrap();
+        assert_eq!(preset.resolve(800, 600), roi(300, 250, 200, 100));
+        assert_eq!(PresetRoi::from_roi(PresetMode::Relative, window, 0, 0), None);
+    }
+}
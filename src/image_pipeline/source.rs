@@ -0,0 +1,308 @@
+//! Contains the video sources the image pipeline can analyze and the elements that feed them into the pipeline.
+
+use std::{convert::Infallible, fmt::Display, path::PathBuf};
+
+use gst::prelude::*;
+
+use crate::image_pipeline::{
+    device_caps::{CapFormat, RawSourceCaps},
+    gstreamer::GstError,
+    image_sequence::ImageSequence,
+    network::NetworkStream,
+    synthetic::{self, SyntheticGenerator, SyntheticParams},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VideoSource {
+    /// v4l2 device with the resolution and framerate currently selected in [`RawSourceCaps`]
+    V4l2,
+    /// video file, decoded with uridecodebin
+    File(PathBuf),
+    /// directory of image files or raw frame dumps
+    ImageSequence(ImageSequence),
+    /// generated frames with known noise parameters
+    Synthetic(SyntheticParams),
+    /// gst-launch style description of the source elements, e.g. "v4l2src ! videoflip method=rotate-180"
+    Custom(String),
+    /// RTSP or RTP/UDP stream of an IP camera, decoded before the analysis
+    Network(NetworkStream),
+}
+
+impl Display for VideoSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VideoSource::V4l2 => write!(f, "v4l2 device"),
+            VideoSource::File(path) => write!(f, "file {}", path.display()),
+            VideoSource::ImageSequence(sequence) => write!(f, "image sequence {}", sequence.dir.display()),
+            VideoSource::Synthetic(_) => write!(f, "synthetic test source"),
+            VideoSource::Custom(description) => write!(f, "custom source \"{description}\""),
+            VideoSource::Network(stream) => write!(f, "network stream {stream}"),
+        }
+    }
+}
+
+/// Negotiated format of the video stream entering the converter, i.e. as delivered by the source.
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+    pub width: i32,
+    pub height: i32,
+    pub framerate: gst::Fraction,
+    pub format: String,
+}
+
+impl VideoSource {
+    /// Returns the device path, file path or URL of the source.
+    pub fn name(&self, caps: &RawSourceCaps) -> String {
+        match self {
+            VideoSource::V4l2 => caps.get_current_device_path().to_string(),
+            VideoSource::File(path) => path.display().to_string(),
+            VideoSource::ImageSequence(sequence) => sequence.dir.display().to_string(),
+            VideoSource::Synthetic(_) => "synthetic".to_string(),
+            VideoSource::Custom(description) => description.clone(),
+            VideoSource::Network(stream) => stream.url.clone(),
+        }
+    }
+
+    /// Returns the v4l2 device path if the source is a v4l2 device.
+    pub fn v4l2_device_path(&self, caps: &RawSourceCaps) -> Option<String> {
+        match self {
+            VideoSource::V4l2 => Some(caps.get_current_device_path().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Creates the source elements, adds them to the pipeline and links them to the given sink element.
+    ///
+    /// The first element is named "source". v4l2 sources are followed by a capsfilter named "filter" and, for
+    /// compressed formats, a parser named "parse" and a decoder named "decode".
+    pub(crate) fn add_to_pipeline(
+        &self,
+        pipeline: &gst::Pipeline,
+        sink: &gst::Element,
+        caps: &RawSourceCaps,
+    ) -> Result<(), GstError> {
+        match self {
+            VideoSource::V4l2 => {
+                let source = gst::ElementFactory::make("v4l2src")
+                    .name("source")
+                    .property("device", caps.get_current_device_path())
+                    .build()?;
+
+                let res = caps.get_current_resolution();
+                let framerate = caps.get_current_framerate()?;
+                let format = caps.get_current_format();
+                println!(
+                    "Selected format: {format}, resolution: {}, framerate: {}",
+                    &res, &framerate
+                );
+                let device_caps = gst::Caps::builder(format.media_type())
+                    .field("width", res.width)
+                    .field("height", res.height)
+                    .field("framerate", framerate)
+                    .build();
+                let capsfilter = gst::ElementFactory::make("capsfilter")
+                    .name("filter")
+                    .property("caps", &device_caps)
+                    .build()?;
+
+                let mut elements = vec![source, capsfilter];
+                match format {
+                    CapFormat::Raw => {}
+                    CapFormat::Jpeg => {
+                        elements.push(gst::ElementFactory::make("jpegdec").name("decode").build()?);
+                    }
+                    CapFormat::H264 => {
+                        elements.push(gst::ElementFactory::make("h264parse").name("parse").build()?);
+                        elements.push(gst::ElementFactory::make("avdec_h264").name("decode").build()?);
+                    }
+                }
+
+                pipeline.add_many(&elements)?;
+                gst::Element::link_many(elements.iter().chain([sink]))?;
+            }
+            VideoSource::File(path) => {
+                let uri = gst::glib::filename_to_uri(path, None)?;
+                let source = gst::ElementFactory::make("uridecodebin")
+                    .name("source")
+                    .property("uri", uri.as_str())
+                    .build()?;
+
+                // decoded pads appear once the file is parsed, only the first video stream is analyzed
+                let sink_weak = sink.downgrade();
+                source.connect_pad_added(move |_, src_pad| {
+                    let Some(sink) = sink_weak.upgrade() else {
+                        return;
+                    };
+                    let sink_pad = sink.static_pad("sink").expect("Converter has no sink pad.");
+                    if sink_pad.is_linked() {
+                        return;
+                    }
+                    if !is_video_pad(src_pad) {
+                        return;
+                    }
+                    if let Err(e) = src_pad.link(&sink_pad) {
+                        eprintln!("Could not link decoded video stream: {e}");
+                    }
+                });
+
+                pipeline.add(&source)?;
+            }
+            VideoSource::ImageSequence(sequence) => {
+                let source = sequence.create_source()?;
+                pipeline.add(&source)?;
+                source.link(sink)?;
+            }
+            VideoSource::Synthetic(params) => {
+                let info = gst_video::VideoInfo::builder(gst_video::VideoFormat::Gray8, params.width, params.height)
+                    .fps(gst::Fraction::new(synthetic::FRAMERATE, 1))
+                    .build()?;
+                let mut generator = SyntheticGenerator::new(*params);
+                let source = create_app_source(info, move || Ok::<_, Infallible>(generator.next_frame()))?;
+                pipeline.add(&source)?;
+                source.link(sink)?;
+            }
+            VideoSource::Custom(description) => {
+                // unlinked pads of the description are exposed as ghost pads of the bin
+                let bin = gst::parse::bin_from_description(description, true)
+                    .map_err(|e| GstError::InvalidDescription(e.message().to_string()))?;
+                if bin.static_pad("src").is_none() {
+                    return Err(GstError::InvalidDescription(
+                        "the description has no unlinked source pad".to_string(),
+                    ));
+                }
+                bin.set_property("name", "source");
+                pipeline.add(&bin)?;
+                bin.link(sink)
+                    .map_err(|e| GstError::InvalidDescription(format!("{e}, check the output format")))?;
+            }
+            VideoSource::Network(stream) => {
+                let source = stream.create_source(sink)?;
+                pipeline.add(&source)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the source elements added by [`VideoSource::add_to_pipeline`] from the pipeline.
+    pub(crate) fn remove_from_pipeline(pipeline: &gst::Pipeline) -> Result<(), GstError> {
+        for name in ["source", "filter", "parse", "decode"] {
+            if let Some(element) = pipeline.by_name(name) {
+                pipeline.remove(&element)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether the pad carries video, e.g. a decoded pad of a decodebin.
+pub(crate) fn is_video_pad(pad: &gst::Pad) -> bool {
+    let caps = pad.current_caps().unwrap_or_else(|| pad.query_caps(None));
+    caps.structure(0)
+        .map(|s| s.name().starts_with("video/"))
+        .unwrap_or(false)
+}
+
+/// Returns the presentation time of the given frame at the given framerate.
+pub(crate) fn frame_time(frame: u64, framerate: gst::Fraction) -> Option<gst::ClockTime> {
+    if framerate.numer() <= 0 || framerate.denom() <= 0 {
+        return None;
+    }
+    let ns = frame as u128 * 1_000_000_000 * framerate.denom() as u128 / framerate.numer() as u128;
+    Some(gst::ClockTime::from_nseconds(ns as u64))
+}
+
+/// Creates an appsrc named "source" that pushes the frames returned by `next_frame` with continuous timestamps.
+///
+/// Frames are given without row padding in the format of the video info. An error ends the stream.
+pub(crate) fn create_app_source<F, E>(info: gst_video::VideoInfo, mut next_frame: F) -> Result<gst::Element, GstError>
+where
+    F: FnMut() -> Result<Vec<u8>, E> + Send + 'static,
+    E: Display,
+{
+    let appsrc = gst::ElementFactory::make("appsrc")
+        .name("source")
+        .property("caps", &info.to_caps()?)
+        .property("format", gst::Format::Time)
+        .build()?
+        .downcast::<gst_app::AppSrc>()
+        .expect("Could not downcast Element to AppSrc.");
+
+    let planes = frame_planes(&info);
+    let framerate = info.fps();
+    let mut frame_idx = 0;
+
+    appsrc.set_callbacks(
+        gst_app::AppSrcCallbacks::builder()
+            .need_data(move |appsrc, _| {
+                let frame = match next_frame() {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        eprintln!("Reading frame failed: {e}");
+                        let _ = appsrc.end_of_stream();
+                        return;
+                    }
+                };
+
+                let Ok(mut buffer) = gst::Buffer::with_size(info.size()) else {
+                    eprintln!("Could not allocate frame buffer.");
+                    return;
+                };
+                {
+                    let buffer = buffer.get_mut().expect("New buffer is not writable.");
+                    let pts = frame_time(frame_idx, framerate);
+                    buffer.set_pts(pts);
+                    buffer.set_duration(frame_time(frame_idx + 1, framerate).and_then(|end| end.checked_sub(pts?)));
+
+                    let Ok(mut map) = buffer.map_writable() else {
+                        eprintln!("Failed to map buffer writable.");
+                        return;
+                    };
+                    let data = map.as_mut_slice();
+                    let mut rows = frame.as_slice();
+                    for (plane, &(row_bytes, n_rows)) in planes.iter().enumerate() {
+                        let stride = info.stride()[plane] as usize;
+                        let offset = info.offset()[plane];
+                        for (row_idx, row) in rows.chunks_exact(row_bytes).take(n_rows).enumerate() {
+                            let start = offset + row_idx * stride;
+                            data[start..start + row_bytes].copy_from_slice(row);
+                        }
+                        rows = rows.get(row_bytes * n_rows..).unwrap_or_default();
+                    }
+                }
+                frame_idx += 1;
+
+                if let Err(e) = appsrc.push_buffer(buffer) {
+                    eprintln!("Pushing frame failed: {e}");
+                }
+            })
+            .build(),
+    );
+
+    Ok(appsrc.upcast())
+}
+
+/// Returns the bytes of a row and the number of rows of every plane of a frame without row padding.
+pub(crate) fn frame_planes(info: &gst_video::VideoInfo) -> Vec<(usize, usize)> {
+    let format_info = info.format_info();
+    (0..info.n_planes() as usize)
+        .map(|plane| {
+            // the first component of a plane determines its size, e.g. U of the interleaved U and V plane of NV12
+            let component = (0..format_info.n_components() as usize)
+                .find(|&c| format_info.plane()[c] as usize == plane)
+                .unwrap_or(0);
+            let width = format_info.scale_width(component as u8, info.width()) as usize;
+            let row_bytes = format_info.pixel_stride()[component] as usize * width;
+            let rows = format_info.scale_height(component as u8, info.height()) as usize;
+            (row_bytes, rows)
+        })
+        .collect()
+}
+
+/// Returns the size of a frame without row padding.
+pub(crate) fn frame_bytes(info: &gst_video::VideoInfo) -> usize {
+    frame_planes(info)
+        .iter()
+        .map(|(row_bytes, rows)| row_bytes * rows)
+        .sum()
+}
@@ -0,0 +1,184 @@
+//! Switching of the video source as transition of a small state machine.
+//!
+//! Every switch, including a new resolution or framerate of the same v4l2 device, stops the pipeline in null state,
+//! rebuilds the source elements so that the caps are negotiated anew and returns the pipeline to its previous state.
+//! If the new source fails to start, the previous source and its caps are restored.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use gst::{prelude::*, State};
+use thiserror::Error;
+
+use crate::image_pipeline::{device_caps::RawSourceCaps, gstreamer::GstError, source::VideoSource};
+
+/// Time the new source has to reach the target state, sources that take longer are kept and reported by the bus.
+const NEGOTIATION_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(3);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SwitchState {
+    /// the current source is set up, the pipeline is playing or paused
+    Running,
+    /// the pipeline is going to null state
+    Stopping,
+    /// the source elements are replaced
+    Building,
+    /// the pipeline returns to its previous state and negotiates the caps of the new source
+    Negotiating,
+    /// the new source failed and the previous source could not be restored
+    Failed,
+}
+
+#[derive(Debug, Error)]
+pub enum SwitchError {
+    #[error("Another source switch is in progress.")]
+    Busy,
+
+    #[error("Invalid selection: {0}")]
+    InvalidSelection(GstError),
+
+    #[error("Switching to {target} failed: {error}. The previous source was restored.")]
+    Restored { target: String, error: GstError },
+
+    #[error("Switching to {target} failed: {error}. Restoring the previous source failed too: {restore_error}")]
+    Failed {
+        target: String,
+        error: GstError,
+        restore_error: Box<GstError>,
+    },
+}
+
+pub struct SourceSwitcher {
+    pipeline: gst::Pipeline,
+    caps: Arc<Mutex<RawSourceCaps>>,
+    source: Arc<Mutex<VideoSource>>,
+    state: Mutex<SwitchState>,
+    // counts the switches, so that measurements of the previous source can be discarded
+    generation: AtomicU64,
+}
+
+impl SourceSwitcher {
+    pub(crate) fn new(
+        pipeline: gst::Pipeline,
+        caps: Arc<Mutex<RawSourceCaps>>,
+        source: Arc<Mutex<VideoSource>>,
+    ) -> Self {
+        SourceSwitcher {
+            pipeline,
+            caps,
+            source,
+            state: Mutex::new(SwitchState::Running),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of source switches so far.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Switches to the given source.
+    pub(crate) fn switch_to(&self, target: VideoSource) -> Result<(), SwitchError> {
+        self.transition(target, |_| Ok(()), None)
+    }
+
+    /// Switches to the v4l2 capability with the given index at its first framerate.
+    pub(crate) fn switch_to_v4l2(&self, cap_idx: usize) -> Result<(), SwitchError> {
+        self.transition(VideoSource::V4l2, |caps| caps.set_resolution(cap_idx), None)
+    }
+
+    /// Switches the v4l2 source to the framerate with the given index.
+    pub(crate) fn set_framerate(&self, framerate_idx: usize) -> Result<(), SwitchError> {
+        self.transition(VideoSource::V4l2, |caps| caps.set_framerate(framerate_idx), None)
+    }
+
+    /// Rebuilds the current source and starts playing, e.g. after an error stopped the pipeline.
+    pub(crate) fn restart(&self) -> Result<(), SwitchError> {
+        let source = self.source.lock().expect("Source Mutex poisened").clone();
+        self.transition(source, |_| Ok(()), Some(State::Playing))
+    }
+
+    fn transition(
+        &self,
+        target: VideoSource,
+        select_caps: impl FnOnce(&mut RawSourceCaps) -> Result<(), GstError>,
+        target_state: Option<State>,
+    ) -> Result<(), SwitchError> {
+        {
+            let mut state = self.state.lock().expect("Switch state Mutex poisened");
+            if !matches!(*state, SwitchState::Running | SwitchState::Failed) {
+                return Err(SwitchError::Busy);
+            }
+            *state = SwitchState::Stopping;
+        }
+
+        // the source is cloned before the caps are locked, the caps must not be locked while holding the source
+        let previous_source = self.source.lock().expect("Source Mutex poisened").clone();
+        let mut caps = self.caps.lock().expect("Caps Mutex poisened");
+        let previous_caps = caps.clone();
+        if let Err(e) = select_caps(&mut caps) {
+            self.set_state(SwitchState::Running);
+            return Err(SwitchError::InvalidSelection(e));
+        }
+
+        let target_state = target_state.unwrap_or(match self.pipeline.current_state() {
+            State::Playing => State::Playing,
+            _ => State::Paused,
+        });
+
+        let result = match self.rebuild(&target, &caps, target_state) {
+            Ok(()) => {
+                *self.source.lock().expect("Source Mutex poisened") = target;
+                self.set_state(SwitchState::Running);
+                Ok(())
+            }
+            Err(error) => {
+                *caps = previous_caps;
+                match self.rebuild(&previous_source, &caps, target_state) {
+                    Ok(()) => {
+                        self.set_state(SwitchState::Running);
+                        Err(SwitchError::Restored {
+                            target: target.to_string(),
+                            error,
+                        })
+                    }
+                    Err(restore_error) => {
+                        self.set_state(SwitchState::Failed);
+                        Err(SwitchError::Failed {
+                            target: target.to_string(),
+                            error,
+                            restore_error: Box::new(restore_error),
+                        })
+                    }
+                }
+            }
+        };
+
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        result
+    }
+
+    // stops the pipeline, replaces the source elements and brings the pipeline to the target state
+    fn rebuild(&self, source: &VideoSource, caps: &RawSourceCaps, target_state: State) -> Result<(), GstError> {
+        self.set_state(SwitchState::Stopping);
+        self.pipeline.set_state(State::Null)?;
+
+        self.set_state(SwitchState::Building);
+        VideoSource::remove_from_pipeline(&self.pipeline)?;
+        let convert = self.pipeline.by_name("convert").ok_or(GstError::ElementNotFound)?;
+        source.add_to_pipeline(&self.pipeline, &convert, caps)?;
+
+        self.set_state(SwitchState::Negotiating);
+        self.pipeline.set_state(target_state)?;
+        // a state change that is still pending after the timeout is not a failure, e.g. a slow network stream
+        let (result, _, _) = self.pipeline.state(Some(NEGOTIATION_TIMEOUT));
+        result?;
+        Ok(())
+    }
+
+    fn set_state(&self, state: SwitchState) {
+        *self.state.lock().expect("Switch state Mutex poisened") = state;
+    }
+}
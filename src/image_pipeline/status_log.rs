@@ -0,0 +1,184 @@
+//! GUI glue of the status bar and the log panel: pipeline bus messages and source events are shown to the user,
+//! together with the recovery actions restarting the pipeline and switching to another source.
+
+use std::{fmt::Display, sync::Arc};
+
+use chrono::{DateTime, Local};
+use gst::{prelude::*, MessageView, State};
+use slint::{ComponentHandle, Model, ModelRc, SharedString, StandardListViewItem, VecModel};
+
+use crate::image_pipeline::{bus::BusWatcher, source_switch::SourceSwitcher};
+use crate::App;
+
+// older entries are dropped from the log panel
+const MAX_LOG_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LogLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevel::Info => write!(f, "info"),
+            LogLevel::Warning => write!(f, "warning"),
+            LogLevel::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct LogEntry {
+    pub time: DateTime<Local>,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+impl Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}: {}", self.time.format("%H:%M:%S"), self.level, self.message)
+    }
+}
+
+impl LogEntry {
+    pub(crate) fn new(level: LogLevel, message: impl Into<String>) -> Self {
+        LogEntry {
+            time: Local::now(),
+            level,
+            message: message.into(),
+        }
+    }
+
+    /// Describes the error, warning, end of stream, state change and latency messages of the pipeline bus.
+    fn from_message(msg: &gst::Message, pipeline: &gst::Pipeline) -> Option<Self> {
+        let src = msg.src().map(|s| s.name().to_string()).unwrap_or_default();
+        match msg.view() {
+            MessageView::Error(err) => {
+                let debug = err.debug().map(|d| format!(" ({d})")).unwrap_or_default();
+                Some(LogEntry::new(LogLevel::Error, format!("{src}: {}{debug}", err.error())))
+            }
+            MessageView::Warning(warning) => {
+                let debug = warning.debug().map(|d| format!(" ({d})")).unwrap_or_default();
+                Some(LogEntry::new(
+                    LogLevel::Warning,
+                    format!("{src}: {}{debug}", warning.error()),
+                ))
+            }
+            MessageView::Eos(_) => Some(LogEntry::new(LogLevel::Info, "End of stream reached.")),
+            // state changes of the single elements are too many to show
+            MessageView::StateChanged(state) if msg.src() == Some(pipeline.upcast_ref::<gst::Object>()) => {
+                Some(LogEntry::new(
+                    LogLevel::Info,
+                    format!(
+                        "Pipeline state changed from {:?} to {:?}.",
+                        state.old(),
+                        state.current()
+                    ),
+                ))
+            }
+            MessageView::Latency(_) => Some(LogEntry::new(
+                LogLevel::Info,
+                format!("Latency of {src} changed, pipeline latency recalculated."),
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl App {
+    pub(crate) fn init_status_log(
+        self: &Arc<App>,
+        bus: &BusWatcher,
+        pipeline: gst::Pipeline,
+        switcher: Arc<SourceSwitcher>,
+    ) {
+        self.set_log_entries(ModelRc::new(VecModel::<StandardListViewItem>::default()));
+
+        bus.add_handler({
+            let ui_weak = self.as_weak();
+            let pipeline = pipeline.clone();
+            move |msg| {
+                // elements changed their latency, so the latency of the pipeline has to be distributed again
+                if let MessageView::Latency(_) = msg.view() {
+                    if let Err(e) = pipeline.recalculate_latency() {
+                        eprintln!("Recalculating latency failed: {e}");
+                    }
+                }
+
+                let Some(entry) = LogEntry::from_message(msg, &pipeline) else {
+                    return;
+                };
+                let is_error = entry.level == LogLevel::Error;
+                let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+                    ui.add_log_entry(entry);
+                    // the pipeline stops on errors
+                    if is_error {
+                        ui.set_playing(false);
+                    }
+                });
+            }
+        });
+
+        self.on_show_log({
+            let ui = self.clone();
+            move || ui.set_show_log_panel(true)
+        });
+
+        self.on_close_log_panel({
+            let ui = self.clone();
+            move || ui.set_show_log_panel(false)
+        });
+
+        self.on_restart_pipeline({
+            let ui = self.clone();
+            move || match switcher.restart() {
+                Ok(()) => {
+                    ui.set_playing(true);
+                    ui.add_log_entry(LogEntry::new(LogLevel::Info, "Pipeline restarted."));
+                }
+                Err(e) => {
+                    ui.set_playing(pipeline.current_state() == State::Playing);
+                    ui.add_log_entry(LogEntry::new(LogLevel::Error, format!("Restarting failed: {e}")));
+                }
+            }
+        });
+
+        self.on_switch_source({
+            let ui = self.clone();
+            move |idx| {
+                let Some(name) = ui.get_video_sources().row_data(idx as usize) else {
+                    return;
+                };
+                ui.set_show_log_panel(false);
+                ui.set_current_video_source(idx);
+                ui.invoke_selected_video_source(name);
+            }
+        });
+    }
+
+    /// Shows the entry in the status bar and appends it to the log panel.
+    pub(crate) fn add_log_entry(&self, entry: LogEntry) {
+        match entry.level {
+            LogLevel::Error => eprintln!("{entry}"),
+            _ => println!("{entry}"),
+        }
+
+        self.set_status_text(SharedString::from(entry.to_string()));
+        self.set_status_level(match entry.level {
+            LogLevel::Info => 0,
+            LogLevel::Warning => 1,
+            LogLevel::Error => 2,
+        });
+
+        let model = self.get_log_entries();
+        if let Some(entries) = model.as_any().downcast_ref::<VecModel<StandardListViewItem>>() {
+            if entries.row_count() >= MAX_LOG_ENTRIES {
+                entries.remove(0);
+            }
+            entries.push(StandardListViewItem::from(SharedString::from(entry.to_string())));
+        }
+    }
+}
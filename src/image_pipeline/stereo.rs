@@ -0,0 +1,395 @@
+//! Synchronized capture of two sensors, e.g. of a stereo rig whose sensors share a power rail.
+//!
+//! The frames of the main camera and of one additional camera are paired by their capture time. For every batch of
+//! paired frames the temporal correlation between the ROI means and between the row noise of the two sensors is
+//! calculated. A correlation close to 1 or -1 shows interference that is coupled into both sensors, uncoupled noise
+//! gives a correlation close to 0.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use gst::ClockTime;
+use ndarray::prelude::*;
+use slint::Weak;
+
+use crate::{image_pipeline::noise::MetricView, App};
+
+/// Largest capture time difference of paired frames if the framerate of the main camera is unknown.
+const DEFAULT_PAIR_TOLERANCE: ClockTime = ClockTime::from_mseconds(8);
+/// Frames of one sensor kept while waiting for the frames of the other sensor.
+const MAX_PENDING: usize = 64;
+
+/// Luminance statistics of the noise window of a single frame.
+#[derive(Debug, Clone)]
+pub(crate) struct StereoSample {
+    pub capture_time: ClockTime,
+    pub nominal_interval: Option<ClockTime>,
+    /// number of frames of the noise window stack, a batch of pairs is as long as a stack of the main camera
+    pub n_frames: usize,
+    pub roi_mean: f32,
+    /// mean of every row of the noise window
+    pub row_means: Array1<f32>,
+}
+
+/// Correlation of the two sensors over one batch of paired frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct StereoCorrelation {
+    pub pairs: usize,
+    /// frames without a frame of the other sensor within the pairing tolerance
+    pub unpaired: u64,
+    /// mean capture time of the partner camera relative to the main camera
+    pub mean_offset_ms: f64,
+    /// Pearson correlation of the ROI means of the paired frames
+    pub roi_mean: f64,
+    /// Pearson correlation of the row means after removing the fixed row pattern, over the rows both windows have
+    pub row_noise: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    Main,
+    Partner,
+}
+
+enum StereoMessage {
+    Sample(Side, StereoSample),
+    Reset,
+}
+
+/// Pairs the frames of the main camera with the frames of the selected partner camera.
+pub(crate) struct StereoPairing {
+    // id of the camera paired with the main camera, None if the stereo mode is off
+    partner: Mutex<Option<i32>>,
+    tx: Sender<StereoMessage>,
+}
+
+impl StereoPairing {
+    /// Starts the correlation thread, the results are shown in the camera panel.
+    pub(crate) fn start(ui: Weak<App>) -> Arc<Self> {
+        let (tx, rx) = mpsc::channel::<StereoMessage>();
+        thread::spawn(move || {
+            let mut correlator = Correlator::default();
+            for msg in rx {
+                let result = match msg {
+                    StereoMessage::Sample(side, sample) => correlator.add(side, sample),
+                    StereoMessage::Reset => {
+                        correlator = Correlator::default();
+                        None
+                    }
+                };
+                let Some(result) = result else {
+                    continue;
+                };
+
+                let status = format!(
+                    "{} pairs, offset {:+.2} ms, {} unpaired, ROI mean correlation {:.3}, row noise correlation {:.3}",
+                    result.pairs, result.mean_offset_ms, result.unpaired, result.roi_mean, result.row_noise
+                );
+                println!("Stereo correlation: {status}");
+                if ui
+                    .upgrade_in_event_loop(move |ui| ui.set_stereo_status(status.into()))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        Arc::new(StereoPairing {
+            partner: Mutex::new(None),
+            tx,
+        })
+    }
+
+    /// Pairs the main camera with the camera of the given id, None turns the stereo mode off.
+    pub(crate) fn set_partner(&self, partner: Option<i32>) {
+        *self.partner.lock().expect("Stereo partner Mutex poisened") = partner;
+        // pairs of the previous partner are discarded
+        let _ = self.tx.send(StereoMessage::Reset);
+    }
+
+    pub(crate) fn partner(&self) -> Option<i32> {
+        *self.partner.lock().expect("Stereo partner Mutex poisened")
+    }
+
+    /// Returns whether the frames of the view take part in the pairing.
+    pub(crate) fn is_paired(&self, view: MetricView) -> bool {
+        self.side(view).is_some()
+    }
+
+    /// Hands the sample of a frame to the pairing, samples of views that are not paired are ignored.
+    pub(crate) fn push(&self, view: MetricView, sample: StereoSample) {
+        if let Some(side) = self.side(view) {
+            let _ = self.tx.send(StereoMessage::Sample(side, sample));
+        }
+    }
+
+    fn side(&self, view: MetricView) -> Option<Side> {
+        let partner = self.partner()?;
+        match view {
+            MetricView::Main => Some(Side::Main),
+            MetricView::Camera(id) if id == partner => Some(Side::Partner),
+            MetricView::Camera(_) => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Correlator {
+    main: VecDeque<StereoSample>,
+    partner: VecDeque<StereoSample>,
+    pairs: Vec<(StereoSample, StereoSample)>,
+    unpaired: u64,
+}
+
+impl Correlator {
+    // returns the correlation once a batch of pairs is complete
+    fn add(&mut self, side: Side, sample: StereoSample) -> Option<StereoCorrelation> {
+        let pending = match side {
+            Side::Main => &mut self.main,
+            Side::Partner => &mut self.partner,
+        };
+        pending.push_back(sample);
+        // the other sensor stopped delivering frames
+        if pending.len() > MAX_PENDING {
+            pending.pop_front();
+            self.unpaired += 1;
+        }
+        self.pair();
+
+        let n_frames = self.pairs.first()?.0.n_frames.max(2);
+        if self.pairs.len() < n_frames {
+            return None;
+        }
+        let result = correlate(&self.pairs, self.unpaired);
+        self.pairs.clear();
+        self.unpaired = 0;
+        Some(result)
+    }
+
+    // pairs the oldest frames of both sensors, a frame without a partner within the tolerance is dropped
+    fn pair(&mut self) {
+        while let (Some(main), Some(partner)) = (self.main.front(), self.partner.front()) {
+            let tolerance = main
+                .nominal_interval
+                .map_or(DEFAULT_PAIR_TOLERANCE.nseconds(), |interval| interval.nseconds() / 2);
+            let (main_time, partner_time) = (main.capture_time.nseconds(), partner.capture_time.nseconds());
+            if main_time.abs_diff(partner_time) <= tolerance {
+                let main = self.main.pop_front().expect("Main sample vanished.");
+                let partner = self.partner.pop_front().expect("Partner sample vanished.");
+                self.pairs.push((main, partner));
+            } else if main_time < partner_time {
+                self.main.pop_front();
+                self.unpaired += 1;
+            } else {
+                self.partner.pop_front();
+                self.unpaired += 1;
+            }
+        }
+    }
+}
+
+fn correlate(pairs: &[(StereoSample, StereoSample)], unpaired: u64) -> StereoCorrelation {
+    let n = pairs.len();
+    let mean_offset_ms = pairs
+        .iter()
+        .map(|(main, partner)| (partner.capture_time.nseconds() as f64 - main.capture_time.nseconds() as f64) / 1e6)
+        .sum::<f64>()
+        / n as f64;
+
+    let roi_main: Vec<f64> = pairs.iter().map(|(main, _)| main.roi_mean as f64).collect();
+    let roi_partner: Vec<f64> = pairs.iter().map(|(_, partner)| partner.roi_mean as f64).collect();
+
+    // the fixed row pattern of each sensor is removed, so that only the temporal row noise is correlated
+    let rows = pairs
+        .iter()
+        .map(|(main, partner)| main.row_means.len().min(partner.row_means.len()))
+        .min()
+        .unwrap_or(0);
+    let row_noise = |side: fn(&(StereoSample, StereoSample)) -> &StereoSample| {
+        let means = Array2::from_shape_fn((n, rows), |(frame, row)| side(&pairs[frame]).row_means[row] as f64);
+        let deviation = &means - &means.mean_axis(Axis(0)).unwrap_or_else(|| Array1::zeros(rows));
+        deviation.into_iter().collect::<Vec<f64>>()
+    };
+
+    StereoCorrelation {
+        pairs: n,
+        unpaired,
+        mean_offset_ms,
+        roi_mean: pearson(&roi_main, &roi_partner),
+        row_noise: pearson(&row_noise(|pair| &pair.0), &row_noise(|pair| &pair.1)),
+    }
+}
+
+/// Pearson correlation coefficient, 0 if one of the signals is constant.
+fn pearson(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len().min(b.len());
+    if n == 0 {
+        return 0.0;
+    }
+    let mean_a = a[..n].iter().sum::<f64>() / n as f64;
+    let mean_b = b[..n].iter().sum::<f64>() / n as f64;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a[..n].iter().zip(&b[..n]) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    if var_a <= 0.0 || var_b <= 0.0 {
+        return 0.0;
+    }
+    cov / (var_a * var_b).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N_FRAMES: usize = 8;
+    const ROWS: usize = 6;
+    // 30 fps, frames are paired within half the frame interval
+    const INTERVAL_MS: u64 = 33;
+
+    // deterministic temporal noise of a frame, shared by both sensors if they are coupled
+    fn noise(frame: usize, row: usize) -> f32 {
+        ((frame * 7 + row * 13) % 11) as f32
+    }
+
+    fn sample(capture_ms: u64, roi_mean: f32, row_means: Array1<f32>) -> StereoSample {
+        StereoSample {
+            capture_time: ClockTime::from_mseconds(capture_ms),
+            nominal_interval: Some(ClockTime::from_mseconds(INTERVAL_MS)),
+            n_frames: N_FRAMES,
+            roi_mean,
+            row_means,
+        }
+    }
+
+    // frame of a sensor with its own fixed row pattern and the shared temporal noise
+    fn coupled(frame: usize, offset_ms: u64, pattern: f32) -> StereoSample {
+        let row_means = Array1::from_shape_fn(ROWS, |row| pattern * row as f32 + noise(frame, row));
+        let roi_mean = 100.0 + pattern + noise(frame, 0);
+        sample(frame as u64 * INTERVAL_MS + offset_ms, roi_mean, row_means)
+    }
+
+    #[test]
+    fn matched_pairs_are_correlated() {
+        let mut correlator = Correlator::default();
+        let mut results = Vec::new();
+        for frame in 0..N_FRAMES {
+            results.extend(correlator.add(Side::Main, coupled(frame, 0, 1.0)));
+            results.extend(correlator.add(Side::Partner, coupled(frame, 2, 5.0)));
+        }
+
+        assert_eq!(results.len(), 1);
+        let result = results[0];
+        assert_eq!(result.pairs, N_FRAMES);
+        assert_eq!(result.unpaired, 0);
+        assert!((result.mean_offset_ms - 2.0).abs() < 1e-9);
+        assert!((result.roi_mean - 1.0).abs() < 1e-9);
+        assert!((result.row_noise - 1.0).abs() < 1e-9);
+        // the next batch starts empty
+        assert!(correlator.pairs.is_empty());
+    }
+
+    #[test]
+    fn inverted_noise_is_anticorrelated() {
+        let pairs: Vec<_> = (0..N_FRAMES)
+            .map(|frame| {
+                let main = coupled(frame, 0, 1.0);
+                let mut partner = coupled(frame, 0, 1.0);
+                partner.roi_mean = -partner.roi_mean;
+                partner.row_means = -partner.row_means;
+                (main, partner)
+            })
+            .collect();
+
+        let result = correlate(&pairs, 0);
+        assert!((result.roi_mean + 1.0).abs() < 1e-9);
+        assert!((result.row_noise + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn frames_without_partner_are_unpaired() {
+        let mut correlator = Correlator::default();
+        let mut results = Vec::new();
+        // the partner misses its fourth frame, the main camera its sixth
+        for frame in 0..=N_FRAMES + 1 {
+            if frame != 5 {
+                results.extend(correlator.add(Side::Main, coupled(frame, 0, 1.0)));
+            }
+            if frame != 3 {
+                results.extend(correlator.add(Side::Partner, coupled(frame, 1, 5.0)));
+            }
+        }
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].pairs, N_FRAMES);
+        assert_eq!(results[0].unpaired, 2);
+        assert!((results[0].roi_mean - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn frames_outside_tolerance_are_not_paired() {
+        let mut correlator = Correlator::default();
+        // more than half a frame interval apart
+        assert!(correlator.add(Side::Main, coupled(0, 0, 1.0)).is_none());
+        assert!(correlator
+            .add(Side::Partner, coupled(0, INTERVAL_MS / 2 + 1, 1.0))
+            .is_none());
+
+        assert!(correlator.pairs.is_empty());
+        assert_eq!(correlator.unpaired, 1);
+        assert_eq!(correlator.partner.len(), 1);
+    }
+
+    #[test]
+    fn pending_frames_are_limited() {
+        let mut correlator = Correlator::default();
+        // the partner camera stopped delivering frames
+        for frame in 0..MAX_PENDING + 5 {
+            assert!(correlator.add(Side::Main, coupled(frame, 0, 1.0)).is_none());
+        }
+
+        assert_eq!(correlator.main.len(), MAX_PENDING);
+        assert_eq!(correlator.unpaired, 5);
+        // the oldest frames were dropped
+        assert_eq!(
+            correlator.main.front().map(|sample| sample.capture_time),
+            Some(ClockTime::from_mseconds(5 * INTERVAL_MS))
+        );
+    }
+
+    #[test]
+    fn constant_signal_has_no_correlation() {
+        assert_eq!(pearson(&[3.0; 5], &[1.0, 2.0, 3.0, 4.0, 5.0]), 0.0);
+        assert_eq!(pearson(&[1.0, 2.0, 3.0], &[7.0; 3]), 0.0);
+        assert_eq!(pearson(&[], &[]), 0.0);
+
+        // a sensor with a constant ROI mean and without temporal row noise
+        let pairs: Vec<_> = (0..N_FRAMES)
+            .map(|frame| {
+                let constant = sample(frame as u64 * INTERVAL_MS, 50.0, Array1::from_elem(ROWS, 50.0));
+                (coupled(frame, 0, 1.0), constant)
+            })
+            .collect();
+        let result = correlate(&pairs, 0);
+        assert_eq!(result.roi_mean, 0.0);
+        assert_eq!(result.row_noise, 0.0);
+    }
+
+    #[test]
+    fn pearson_of_linear_signals() {
+        let a = [1.0, 2.0, 4.0, 8.0];
+        let b: Vec<f64> = a.iter().map(|x| 3.0 * x - 2.0).collect();
+        assert!((pearson(&a, &b) - 1.0).abs() < 1e-12);
+        // the longer signal is cut to the length of the shorter one
+        assert!((pearson(&a, &[2.0, 4.0, 8.0, 16.0, 0.0]) - 1.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,149 @@
+//! Synthetic test source that generates gray frames with known noise parameters to validate the noise analysis.
+
+/// Frames per second the synthetic source is played at.
+pub const FRAMERATE: i32 = 30;
+/// Period of the flicker in frames.
+pub const FLICKER_PERIOD: u64 = 10;
+
+/// Noise parameters of the synthetic frames in digital numbers (DN) of 8 bit gray frames.
+///
+/// The fixed patterns are normalized to zero mean and exactly the given standard deviation, so that the
+/// parameters can be compared directly to the measured noise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyntheticParams {
+    pub width: u32,
+    pub height: u32,
+    /// flat mean level
+    pub level: f32,
+    /// standard deviation of the Gaussian temporal noise of each pixel
+    pub temporal_sigma: f32,
+    /// standard deviation of the per-pixel fixed offsets
+    pub fpn_sigma: f32,
+    /// standard deviation of the fixed per-row offsets
+    pub row_banding: f32,
+    /// standard deviation of the fixed per-column offsets
+    pub column_banding: f32,
+    /// number of pixels stuck at full scale
+    pub hot_pixels: u32,
+    /// standard deviation of the level of the whole frame, modulated as sine over [`FLICKER_PERIOD`] frames
+    pub flicker: f32,
+    pub seed: u64,
+}
+
+impl Default for SyntheticParams {
+    fn default() -> Self {
+        SyntheticParams {
+            width: 640,
+            height: 480,
+            level: 128.0,
+            temporal_sigma: 2.0,
+            fpn_sigma: 1.0,
+            row_banding: 0.5,
+            column_banding: 0.5,
+            hot_pixels: 0,
+            flicker: 0.0,
+            seed: 1,
+        }
+    }
+}
+
+pub(crate) struct SyntheticGenerator {
+    params: SyntheticParams,
+    rng: Rng,
+    // fixed pixel, row and column offsets of every pixel
+    pattern: Vec<f32>,
+    hot_pixels: Vec<usize>,
+    frame_idx: u64,
+}
+
+impl SyntheticGenerator {
+    pub(crate) fn new(params: SyntheticParams) -> Self {
+        let (w, h) = (params.width as usize, params.height as usize);
+        let mut rng = Rng::new(params.seed);
+
+        let fpn = normalized_gaussians(&mut rng, w * h, params.fpn_sigma);
+        let rows = normalized_gaussians(&mut rng, h, params.row_banding);
+        let cols = normalized_gaussians(&mut rng, w, params.column_banding);
+        let pattern = (0..w * h).map(|idx| fpn[idx] + rows[idx / w] + cols[idx % w]).collect();
+
+        let mut hot_pixels = Vec::new();
+        let n_hot = (params.hot_pixels as usize).min(w * h);
+        while hot_pixels.len() < n_hot {
+            let idx = (rng.next_u64() % (w * h) as u64) as usize;
+            if !hot_pixels.contains(&idx) {
+                hot_pixels.push(idx);
+            }
+        }
+
+        SyntheticGenerator {
+            params,
+            rng,
+            pattern,
+            hot_pixels,
+            frame_idx: 0,
+        }
+    }
+
+    /// Generates the next GRAY8 frame without row padding.
+    pub(crate) fn next_frame(&mut self) -> Vec<u8> {
+        // a sine with amplitude sqrt(2) * sigma has the standard deviation sigma over full periods
+        let phase = (self.frame_idx % FLICKER_PERIOD) as f32 / FLICKER_PERIOD as f32 * std::f32::consts::TAU;
+        let level = self.params.level + self.params.flicker * std::f32::consts::SQRT_2 * phase.sin();
+        self.frame_idx += 1;
+
+        let mut frame: Vec<u8> = self
+            .pattern
+            .iter()
+            .map(|offset| {
+                let value = level + offset + self.params.temporal_sigma * self.rng.gaussian();
+                value.round().clamp(0.0, 255.0) as u8
+            })
+            .collect();
+        for &idx in &self.hot_pixels {
+            frame[idx] = u8::MAX;
+        }
+        frame
+    }
+}
+
+/// Returns `n` Gaussian values with zero mean and exactly the standard deviation `sigma`.
+fn normalized_gaussians(rng: &mut Rng, n: usize, sigma: f32) -> Vec<f32> {
+    let values: Vec<f64> = (0..n).map(|_| rng.gaussian() as f64).collect();
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n as f64).sqrt();
+    if std == 0.0 {
+        return vec![0.0; n];
+    }
+    values
+        .iter()
+        .map(|v| ((v - mean) / std * sigma as f64) as f32)
+        .collect()
+}
+
+/// SplitMix64 generator, seeded so that synthetic frames are reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in (0, 1]
+    fn uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    // standard normal distribution by the Box-Muller transform
+    fn gaussian(&mut self) -> f32 {
+        let (u1, u2) = (self.uniform(), self.uniform());
+        ((-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()) as f32
+    }
+}
@@ -0,0 +1,102 @@
+//! Units the noise metrics are shown, entered and exported in.
+//!
+//! The metrics are calculated in DN of the converted 8-bit frames and stored in DN, in the history, the measurement
+//! database, golden references and the applied limits. The selected units only convert what the GUI shows and what is
+//! exported. SNR and defect pixel count have no units and are never converted.
+
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+use slint::{ModelRc, SharedString, VecModel};
+
+use crate::App;
+
+// full scale of the converted 8-bit frames
+const FULL_SCALE_DN: f32 = 255.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Units {
+    /// digital numbers of the 8-bit frames
+    #[default]
+    Dn,
+    /// percent of the 8-bit full scale
+    PercentFullScale,
+}
+
+impl Units {
+    const ALL: [Units; 2] = [Units::Dn, Units::PercentFullScale];
+
+    /// Returns the factor a value in DN is multiplied with to get the value in these units.
+    pub(crate) fn per_dn(self) -> f32 {
+        match self {
+            Units::Dn => 1.0,
+            Units::PercentFullScale => 100.0 / FULL_SCALE_DN,
+        }
+    }
+}
+
+impl Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Units::Dn => write!(f, "DN"),
+            Units::PercentFullScale => write!(f, "% FS"),
+        }
+    }
+}
+
+impl App {
+    /// Sets up the units selection with the given units selected.
+    pub(crate) fn init_units(self: &Arc<App>, units: Units) {
+        let options: Vec<SharedString> = Units::ALL.iter().map(|u| u.to_string().into()).collect();
+        self.set_units_options(ModelRc::new(VecModel::from(options)));
+        self.show_units(units);
+
+        self.on_selected_units({
+            let ui = self.clone();
+            move |_| {
+                ui.show_units(ui.units());
+                // the limit entries are shown in the selected units, the applied limits stay in DN
+                ui.show_limits(&ui.limits());
+            }
+        });
+    }
+
+    /// Returns the selected units.
+    pub(crate) fn units(&self) -> Units {
+        usize::try_from(self.get_units_index())
+            .ok()
+            .and_then(|idx| Units::ALL.get(idx).copied())
+            .unwrap_or_default()
+    }
+
+    fn show_units(&self, units: Units) {
+        let idx = Units::ALL.iter().position(|u| *u == units).unwrap_or_default();
+        self.set_units_index(idx as i32);
+        self.set_units_per_dn(units.per_dn());
+        self.set_units_label(units.to_string().into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_scale_is_hundred_percent() {
+        assert_eq!(FULL_SCALE_DN * Units::PercentFullScale.per_dn(), 100.0);
+        assert_eq!(Units::Dn.per_dn(), 1.0);
+    }
+
+    #[test]
+    fn units_are_stored_by_name() {
+        assert_eq!(
+            serde_json::to_string(&Units::PercentFullScale).unwrap(),
+            "\"percent_full_scale\""
+        );
+        assert_eq!(serde_json::from_str::<Units>("\"dn\"").unwrap(), Units::Dn);
+    }
+}
@@ -0,0 +1,84 @@
+//! Contains functionality to read v4l2 device controls, e.g. sensor temperature, gain or exposure.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    time::{Duration, Instant},
+};
+
+use v4l::{
+    control::{Type, Value},
+    prelude::*,
+};
+
+// cached controls are read again after this time, e.g. to follow an automatic exposure
+const CONTROL_REFRESH: Duration = Duration::from_secs(10);
+
+/// Reads the value of the integer or boolean control with the given (case insensitive) name.
+pub(crate) fn read_control(device_path: &str, name: &str) -> io::Result<i64> {
+    let device = Device::with_path(device_path)?;
+    let description = device
+        .query_controls()?
+        .into_iter()
+        .find(|c| c.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No control named {name:?} on {device_path}."),
+            )
+        })?;
+
+    match device.control(description.id)?.value {
+        Value::Integer(v) => Ok(v),
+        Value::Boolean(b) => Ok(b as i64),
+        v => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Control {name:?} has unsupported value {v:?}."),
+        )),
+    }
+}
+
+/// Reads all integer, boolean and menu controls of the device as (name, value) pairs.
+pub(crate) fn read_all_controls(device_path: &str) -> io::Result<Vec<(String, i64)>> {
+    let device = Device::with_path(device_path)?;
+    let controls = device
+        .query_controls()?
+        .into_iter()
+        .filter(|c| matches!(c.typ, Type::Integer | Type::Integer64 | Type::Boolean | Type::Menu))
+        .filter_map(|c| match device.control(c.id).map(|ctrl| ctrl.value) {
+            Ok(Value::Integer(v)) => Some((c.name, v)),
+            Ok(Value::Boolean(b)) => Some((c.name, b as i64)),
+            // e.g. inactive controls cannot be read while streaming
+            _ => None,
+        })
+        .collect();
+
+    Ok(controls)
+}
+
+/// Controls of the measured device, so that they are not read from the device with every measurement.
+#[derive(Debug, Default)]
+pub(crate) struct ControlCache {
+    // device path, time of reading and controls
+    cached: Option<(String, Instant, BTreeMap<String, i64>)>,
+}
+
+impl ControlCache {
+    /// Returns the controls of the device, they are read again if the device changed, the cache was cleared or the
+    /// controls are older than `CONTROL_REFRESH`.
+    pub(crate) fn controls(&mut self, device_path: &str) -> io::Result<BTreeMap<String, i64>> {
+        if let Some((path, read_at, controls)) = &self.cached {
+            if path == device_path && read_at.elapsed() < CONTROL_REFRESH {
+                return Ok(controls.clone());
+            }
+        }
+        let controls: BTreeMap<String, i64> = read_all_controls(device_path)?.into_iter().collect();
+        self.cached = Some((device_path.to_string(), Instant::now(), controls.clone()));
+        Ok(controls)
+    }
+
+    /// Discards the cached controls, e.g. after the source or capability changed.
+    pub(crate) fn clear(&mut self) {
+        self.cached = None;
+    }
+}
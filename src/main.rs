@@ -1,23 +1,40 @@
 mod image_pipeline;
 
-use image_pipeline::ImagePipeline;
+use image_pipeline::{ImagePipeline, Settings};
 
-use std::error::Error;
+use std::{error::Error, process::ExitCode};
 
 slint::slint! {
-    export { App } from "ui/app-window.slint";
+    export {
+        App, CameraCompareRow, CameraTile, CompareRow, RegressionRow, SyntheticSettings
+    } from "ui/app-window.slint";
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let mut settings = Settings::from_args(std::env::args().skip(1))?;
     let ui = App::new()?;
 
-    let mut img_pipe = ImagePipeline::new().expect("Failed to create image pipeline");
-    ui.link_with_image_pipeline(&mut img_pipe)
+    let mut img_pipe = ImagePipeline::new(&mut settings).expect("Failed to create image pipeline");
+    ui.link_with_image_pipeline(&mut img_pipe, &settings)
         .expect("Failed to link image pipeline to GUI.");
 
+    // a headless run plays the source of the settings without a window until the first measurement is done, the
+    // session state is not saved
+    if settings.headless {
+        ui.invoke_toggle_play_pause();
+        slint::run_event_loop_until_quit()?;
+        let _ = img_pipe.set_state(gst::State::Null);
+        return Ok(ui.verdict_exit_code());
+    }
+
     ui.run()?;
 
+    let path = Settings::default_path();
+    if let Err(e) = ui.session_settings(&img_pipe, &settings).save(&path) {
+        eprintln!("Could not save settings to {}: {e}", path.display());
+    }
+
     let _ = img_pipe.set_state(gst::State::Null);
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }